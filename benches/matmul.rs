@@ -0,0 +1,55 @@
+//! Compares the tiled matmul kernel against the naive reference on square matrices.
+//! Run with `cargo bench --bench matmul`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use llm_rs::operations::matmul;
+
+type Kernel = fn(&[f64], &[f64], &mut [f64], (usize, usize, usize));
+
+/// Deterministic pseudo-random values in [-1, 1) so runs are comparable.
+fn matrix(size: usize, seed: u64) -> Vec<f64> {
+    let mut state = seed;
+    (0..size * size)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+        })
+        .collect()
+}
+
+/// Fastest of a few runs, with fewer runs for larger sizes.
+fn time(kernel: Kernel, a: &[f64], b: &[f64], size: usize) -> Duration {
+    let runs = (1 << 24) / (size * size * size) + 1;
+    (0..runs.min(20))
+        .map(|_| {
+            let mut out = vec![0.0; size * size];
+            let start = Instant::now();
+            kernel(black_box(a), black_box(b), &mut out, (size, size, size));
+            black_box(&out);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>12} {:>12} {:>8}",
+        "size", "naive", "tiled", "speedup"
+    );
+    for size in [64, 256, 1024] {
+        let (a, b) = (matrix(size, 1), matrix(size, 2));
+        let naive = time(matmul::naive, &a, &b, size);
+        let tiled = time(matmul::tiled, &a, &b, size);
+        println!(
+            "{:>6} {:>12.3?} {:>12.3?} {:>7.1}x",
+            size,
+            naive,
+            tiled,
+            naive.as_secs_f64() / tiled.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,12 @@
+// Links the system CBLAS when the `blas` feature is on. `BLAS_LIB` names the library, e.g.
+// `BLAS_LIB=cblas` for netlib; it defaults to OpenBLAS.
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=BLAS_LIB");
+    if env::var_os("CARGO_FEATURE_BLAS").is_some() {
+        let library = env::var("BLAS_LIB").unwrap_or_else(|_| "openblas".to_string());
+        println!("cargo:rustc-link-lib={library}");
+    }
+}
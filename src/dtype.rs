@@ -0,0 +1,65 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+
+use num_traits::NumAssign;
+
+use crate::operations::{matmul::Gemm, simd::Simd};
+
+/// A type that can be stored in a `Tensor`. Casts between element types go through `f64`,
+/// which is exact for every supported type except `i64` values beyond 2^53.
+pub trait Element: Copy + Default + PartialEq + PartialOrd + Debug + Display + 'static {
+    fn from_f64(value: f64) -> Self;
+    fn into_f64(self) -> f64;
+}
+
+/// Floating-point elements. Only tensors of these take part in autograd. `Send + Sync` lets
+/// kernels split the work on a tensor's data across threads.
+pub trait Float: Element + num_traits::Float + NumAssign + Sum + Send + Sync + Gemm + Simd {}
+
+impl Element for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Element for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Element for i64 {
+    /// Truncates towards zero, saturating at the bounds of `i64`.
+    fn from_f64(value: f64) -> Self {
+        value as i64
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Element for bool {
+    /// Any nonzero value is `true`.
+    fn from_f64(value: f64) -> Self {
+        value != 0.0
+    }
+
+    fn into_f64(self) -> f64 {
+        match self {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+}
+
+impl Float for f64 {}
+impl Float for f32 {}
@@ -3,3 +3,5 @@ pub mod operations;
 pub mod nn;
 pub mod optimizer;
 pub mod data;
+pub mod dtype;
+pub mod storage;
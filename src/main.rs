@@ -27,7 +27,7 @@ fn main() {
             weights.set_grad(Tensor::singleton(0.0));
             bias.set_grad(Tensor::singleton(0.0));
             let (x, y) = (sample.input, sample.output);
-            let y_pred = &(&*weights * &x) + bias;
+            let y_pred = &(&*weights * &x) + &*bias;
             // println!("product: {}", y_pred);
             let loss = &Differentiable::pow(&(y_pred - y.clone()), 2);
             last_loss = loss.clone();
@@ -1,6 +1,9 @@
 use std::{any::Any, cell::RefCell, rc::Rc};
 
-use crate::{operations::Differentiable, tensor::Tensor};
+use crate::{
+    operations::{Differentiable, GeluApproximation},
+    tensor::Tensor,
+};
 
 pub trait Module {
     fn forward(&self, input: Tensor) -> Tensor;
@@ -91,6 +94,114 @@ impl Module for ReLU {
     }
 }
 
+pub struct Sigmoid {}
+impl Module for Sigmoid {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.sigmoid()
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct Tanh {}
+impl Module for Tanh {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.tanh()
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct GELU {
+    pub approximation: GeluApproximation,
+}
+impl Module for GELU {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.gelu(self.approximation)
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct LeakyReLU {
+    pub slope: f64,
+}
+impl Module for LeakyReLU {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.leaky_relu(self.slope)
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct ELU {
+    pub alpha: f64,
+}
+impl Module for ELU {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.elu(self.alpha)
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct SiLU {}
+impl Module for SiLU {
+    fn forward(&self, input: Tensor) -> Tensor {
+        input.silu()
+    }
+
+    fn reset_grad(&self) {}
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Tensor>>> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 pub struct Model {
     pub layers: Vec<Box<dyn Module>>,
 }
@@ -106,9 +217,7 @@ impl Module for Model {
         println!("FORWARD!");
         let mut last_value = input;
         for layer in self.layers.iter() {
-            let temp = last_value.clone();
-            let (x, y) = temp.size;
-            println!("{} - size: ({}, {})", layer.get_name(), x, y);
+            println!("{} - shape: {:?}", layer.get_name(), last_value.shape);
             last_value = layer.forward(last_value);
         }
         last_value
@@ -1,53 +1,768 @@
 use std::{
     cell::RefCell,
-    clone,
-    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    collections::HashSet,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
     rc::Rc,
 };
 
-use crate::tensor::Tensor;
+use crate::{
+    dtype::{Element, Float},
+    storage::Storage,
+    tensor::{broadcast_shape, unravel, Tensor},
+};
+
+mod activation;
+pub mod check;
+mod grad_mode;
+pub mod matmul;
+pub mod simd;
+
+pub use activation::GeluApproximation;
+pub use grad_mode::{is_grad_enabled, no_grad, NoGradGuard};
 
 #[derive(Clone)]
-pub struct Gradient {
-    pub operation: GradientOperation,
-    pub last: Option<Tensor>,
-    pub value: Option<Tensor>, // Shouldn't grad be ties to operation?
+pub struct Gradient<T: Element = f64> {
+    pub operation: GradientOperation<T>,
+    pub value: Option<Tensor<T>>, // Shouldn't grad be ties to operation?
+    /// Whether gradients flow into this node. Set on leaves by `with_grad`, and on op results
+    /// iff any input requires grad. `value` is allocated when the first gradient arrives.
+    pub requires_grad: bool,
 }
 
-fn format_name(tensor: &Tensor) -> String {
-    if !tensor.name.is_empty() {
-        return tensor.name.clone();
+/// Identity permutation of `rank` axes with `first` and `second` exchanged.
+fn swapped_axes(rank: usize, first: usize, second: usize) -> Vec<usize> {
+    let mut axes: Vec<usize> = (0..rank).collect();
+    axes.swap(first, second);
+    axes
+}
+
+/// Longest operand name spelled out in an operation's label. Every output is named after its
+/// operands, so without a bound labels grow exponentially on diamond graphs (and
+/// quadratically along long unrolls); longer names are elided instead.
+const MAX_LABEL_OPERAND: usize = 64;
+
+/// How an operand appears in labels: its name, or its values if it's unnamed and small.
+fn format_name<T: Element>(tensor: &Tensor<T>) -> String {
+    match tensor.name.len() {
+        0 if tensor.data.len() <= 8 => tensor.to_string(),
+        0 => format!("Tensor{:?}", tensor.shape),
+        length if length <= MAX_LABEL_OPERAND => tensor.name.clone(),
+        _ => "…".to_string(),
     }
-    tensor.to_string()
 }
 
-fn unary_label(operation: String, tensor: &Tensor) -> String {
+fn unary_label<T: Element>(operation: String, tensor: &Tensor<T>) -> String {
     let tensor = format_name(tensor);
     format!("({} {})", operation, tensor)
 }
 
-fn binary_label(left: &Tensor, operation: String, right: &Tensor) -> String {
+fn binary_label<T: Element>(left: &Tensor<T>, operation: String, right: &Tensor<T>) -> String {
     let left = format_name(left);
     let right = format_name(right);
     format!("({} {} {})", left, operation, right)
 }
 
-impl Default for Gradient {
+impl<T: Element> Default for Gradient<T> {
     fn default() -> Self {
         Gradient {
             operation: GradientOperation::None,
-            last: None,
             value: None,
+            requires_grad: false,
         }
     }
 }
 
-impl Gradient {
-    pub fn wrap(self) -> Rc<RefCell<Gradient>> {
+impl<T: Element> Gradient<T> {
+    pub fn wrap(self) -> Rc<RefCell<Gradient<T>>> {
         Rc::new(RefCell::new(self))
     }
 }
 
+impl<T: Float> Gradient<T> {
+    /// Adds this node's contribution to the grads of the inputs of its operation.
+    fn propagate(&self) {
+        let grad = match &self.value {
+            Some(value) => value.clone(),
+            None => return,
+        };
+        match &self.operation {
+            GradientOperation::None => {}
+            GradientOperation::Neg(a) => {
+                // y = -a
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad * -1
+                a.add_grad(-grad.clone());
+            }
+            GradientOperation::Add(a, b) => {
+                // y = a + b
+                // Broadcast axes received the same grad at every position, so sum them away.
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad * 1
+                // b.grad = dL/db = (dL/dy)(dy/db) = grad * 1
+                a.add_grad(grad.sum_to(&a.shape));
+                b.add_grad(grad.sum_to(&b.shape));
+            }
+            GradientOperation::Sub(a, b) => {
+                // y = a - b
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad * 1
+                // b.grad = dL/db = (dL/dy)(dy/db) = grad * -1
+                a.add_grad(grad.sum_to(&a.shape));
+                b.add_grad(-grad.sum_to(&b.shape));
+            }
+            GradientOperation::Mul(a, b) => {
+                // y = a * b
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad * b
+                // b.grad = dL/db = (dL/dy)(dy/db) = grad * a
+                //
+                // Y    [... x m x p] = A.B = [... x m x n].[... x n x p]
+                // grad [... x m x p]
+                // A.grad [... x m x n] = grad.(B^T) = [... x m x p].[... x p x n]
+                // B.grad [... x n x p] = (A^T).grad = [... x n x m].[... x m x p]
+                // Batch axes that were broadcast are summed away, as for `Add`.
+                let (a_last, b_last) = (a.last(), b.last());
+                let a_partial = grad.matmul(&b_last.transpose(b.rank() - 2, b.rank() - 1));
+                a.add_grad(a_partial.sum_to(&a.shape));
+                let b_partial = a_last.transpose(a.rank() - 2, a.rank() - 1).matmul(&grad);
+                b.add_grad(b_partial.sum_to(&b.shape));
+            }
+            GradientOperation::MulElem(a, b) => {
+                // y = a ⊙ b
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad ⊙ b
+                // b.grad = dL/db = (dL/dy)(dy/db) = grad ⊙ a
+                let (a_last, b_last) = (a.last(), b.last());
+                a.add_grad(grad.zip_with(&b_last, |g, b| g * b).sum_to(&a.shape));
+                b.add_grad(grad.zip_with(&a_last, |g, a| g * a).sum_to(&b.shape));
+            }
+            GradientOperation::Div(a, b) => {
+                // y = a / b
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad / b
+                // b.grad = dL/db = (dL/dy)(dy/db) = grad * -a / b^2
+                let (a_last, b_last) = (a.last(), b.last());
+                a.add_grad(grad.zip_with(&b_last, |g, b| g / b).sum_to(&a.shape));
+                let quotient = a_last.zip_with(&b_last, |a, b| a / (b * b));
+                b.add_grad(grad.zip_with(&quotient, |g, q| -g * q).sum_to(&b.shape));
+            }
+            GradientOperation::ReLU(a) => {
+                // y = [ x >= 0: x, x < 0: 0 ]
+                // dy/dx = [x >= 0: 1, x < 0: 0]
+                let a_last = a.last();
+                a.add_grad(a_last.zip_with(&grad, |last, grad| match last {
+                    last if last >= T::zero() => grad,
+                    _ => T::zero(),
+                }));
+            }
+            GradientOperation::Pow(a, 0) => {
+                // y = a^0 = 1
+                // a.grad = 0, even at a = 0 where the general rule's a^(b-1) is infinite
+                a.add_grad(Tensor::zeros_like(a));
+            }
+            GradientOperation::Pow(a, b) => {
+                // y = a^b
+                // a.grad = dL/da = (dL/dy)(dy/da) = grad * ba^(b-1)
+                let a_last = a.last();
+                a.add_grad(a_last.zip_with(&grad, |last, grad| {
+                    grad * T::from_f64(*b as f64) * last.powi(b - 1)
+                }));
+            }
+            GradientOperation::Mean(a) => {
+                // y = (a_1 + ... + a_n) / n
+                // a_i.grad = dL/da_i = (dL/dy)(dy/da_i) = grad / n
+                let denominator = T::from_f64(a.num_elements() as f64);
+                // The output is always [1, 1], so build the grad rather than broadcast it: a
+                // rank-0 or rank-1 input can't be broadcast to from [1, 1].
+                a.add_grad(Tensor::full(&a.shape, grad.item() / denominator));
+            }
+            GradientOperation::Exp(a) => {
+                // y = e^a
+                // a.grad = grad * e^a
+                a.add_grad(a.last().zip_with(&grad, |a, g| g * a.exp()));
+            }
+            GradientOperation::Ln(a) => {
+                // y = ln(a)
+                // a.grad = grad / a
+                a.add_grad(a.last().zip_with(&grad, |a, g| g / a));
+            }
+            GradientOperation::Sqrt(a) => {
+                // y = sqrt(a)
+                // a.grad = grad / (2 sqrt(a))
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g / (T::from_f64(2.0) * a.sqrt())),
+                );
+            }
+            GradientOperation::Abs(a) => {
+                // y = |a|
+                // a.grad = grad * sign(a), taking the subgradient at 0 to be 0
+                a.add_grad(a.last().zip_with(&grad, |a, g| match a {
+                    a if a > T::zero() => g,
+                    a if a < T::zero() => -g,
+                    _ => T::zero(),
+                }));
+            }
+            GradientOperation::Sin(a) => {
+                // y = sin(a)
+                // a.grad = grad * cos(a)
+                a.add_grad(a.last().zip_with(&grad, |a, g| g * a.cos()));
+            }
+            GradientOperation::Cos(a) => {
+                // y = cos(a)
+                // a.grad = grad * -sin(a)
+                a.add_grad(a.last().zip_with(&grad, |a, g| -g * a.sin()));
+            }
+            GradientOperation::Powf(a, b) => {
+                // y = a^b
+                // a.grad = grad * ba^(b-1)
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g * *b * a.powf(*b - T::one())),
+                );
+            }
+            GradientOperation::Sigmoid(a) => {
+                // y = 1 / (1 + e^-a)
+                // a.grad = grad * y(1 - y)
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g * activation::sigmoid_derivative(a)),
+                );
+            }
+            GradientOperation::Tanh(a) => {
+                // y = tanh(a)
+                // a.grad = grad * (1 - y^2)
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g * activation::tanh_derivative(a)),
+                );
+            }
+            GradientOperation::GELU(a, approximation) => {
+                // y = aΦ(a)
+                // a.grad = grad * (Φ(a) + aφ(a)), or the derivative of the tanh approximation
+                a.add_grad(a.last().zip_with(&grad, |a, g| {
+                    g * activation::gelu_derivative(a, *approximation)
+                }));
+            }
+            GradientOperation::LeakyReLU(a, slope) => {
+                // y = [ a >= 0: a, a < 0: slope * a ]
+                // a.grad = [ a >= 0: grad, a < 0: slope * grad ]
+                a.add_grad(a.last().zip_with(&grad, |a, g| {
+                    g * activation::leaky_relu_derivative(a, *slope)
+                }));
+            }
+            GradientOperation::ELU(a, alpha) => {
+                // y = [ a > 0: a, a <= 0: alpha(e^a - 1) ]
+                // a.grad = [ a > 0: grad, a <= 0: grad * alpha * e^a ]
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g * activation::elu_derivative(a, *alpha)),
+                );
+            }
+            GradientOperation::SiLU(a) => {
+                // y = a * sigmoid(a)
+                // a.grad = grad * sigmoid(a)(1 + a(1 - sigmoid(a)))
+                a.add_grad(
+                    a.last()
+                        .zip_with(&grad, |a, g| g * activation::silu_derivative(a)),
+                );
+            }
+            GradientOperation::Softmax(a, axis, y) => {
+                // y_i = e^(a_i) / sum_j e^(a_j)
+                // a_i.grad = sum_j grad_j * dy_j/da_i = y_i * (grad_i - sum_j grad_j * y_j)
+                let weighted = grad
+                    .zip_with(y, |g, y| g * y)
+                    .map_lanes(*axis, |lane| vec![lane.iter().copied().sum(); lane.len()]);
+                let difference = grad.zip_with(&weighted, |g, w| g - w);
+                a.add_grad(y.zip_with(&difference, |y, d| y * d));
+            }
+            GradientOperation::LogSoftmax(a, axis, y) => {
+                // y_i = a_i - ln(sum_j e^(a_j))
+                // a_i.grad = grad_i - e^(y_i) * sum_j grad_j
+                let total =
+                    grad.map_lanes(*axis, |lane| vec![lane.iter().copied().sum(); lane.len()]);
+                let scaled = y.zip_with(&total, |y, t| y.exp() * t);
+                a.add_grad(grad.zip_with(&scaled, |g, s| g - s));
+            }
+            GradientOperation::Sum(a, axis) => {
+                // y = sum_i a_i along axis
+                // a_i.grad = grad, repeated along the reduced axis
+                a.add_grad(grad.unreduce(&a.shape, *axis));
+            }
+            GradientOperation::MeanAxis(a, axis) => {
+                // y = sum_i a_i / n along axis
+                // a_i.grad = grad / n
+                let n = T::from_f64(a.shape[*axis] as f64);
+                a.add_grad(grad.unreduce(&a.shape, *axis).map(|g| g / n));
+            }
+            GradientOperation::Max(a, selected)
+            | GradientOperation::Min(a, selected)
+            | GradientOperation::Select(a, selected) => {
+                // y_j = a_k, where k = selected[j]
+                // a_k.grad = sum of grad_j over every j that selected k, otherwise 0
+                let mut partial = Tensor::zeros_like(a);
+                for (g, &offset) in grad.data.iter().zip(selected.iter()) {
+                    partial.data[offset] += *g;
+                }
+                a.add_grad(partial);
+            }
+            GradientOperation::Var(a, axis, unbiased) => {
+                // y = sum_i (a_i - mean)^2 / (n - ddof)
+                // a_i.grad = grad * 2(a_i - mean) / (n - ddof), as the deviations sum to 0
+                let a_last = a.last();
+                let n = T::from_f64(a.shape[*axis] as f64 - if *unbiased { 1.0 } else { 0.0 });
+                let deviations = a_last.map_lanes(*axis, |lane| {
+                    let mean = lane.iter().copied().sum::<T>() / T::from_f64(lane.len() as f64);
+                    lane.iter().map(|&x| x - mean).collect()
+                });
+                let grad = grad.unreduce(&a.shape, *axis);
+                a.add_grad(deviations.zip_with(&grad, |d, g| T::from_f64(2.0) * g * d / n));
+            }
+            GradientOperation::Reshape(a) => {
+                // y = a, laid out with a different shape
+                // a.grad = grad, laid out with a's shape
+                a.add_grad(Tensor::from_storage(&a.shape, grad.data.clone()));
+            }
+            GradientOperation::Permute(a, axes) => {
+                // y[i_0, .., i_n] = a[i_axes[0], .., i_axes[n]]
+                // a.grad = grad permuted by the inverse of axes
+                let mut inverse = vec![0; axes.len()];
+                for (i, &axis) in axes.iter().enumerate() {
+                    inverse[axis] = i;
+                }
+                a.add_grad(grad.permuted(&inverse));
+            }
+            GradientOperation::Transpose(a, first, second) => {
+                // y = a with axes first and second swapped
+                // a.grad = grad with the same axes swapped back
+                a.add_grad(grad.permuted(&swapped_axes(grad.rank(), *first, *second)));
+            }
+            GradientOperation::Cat(pieces, axis) => {
+                // y = [a_0, a_1, ..] along axis
+                // a_i.grad = the slice of grad that a_i was copied into
+                let mut start = 0;
+                for piece in pieces {
+                    let len = piece.shape[*axis];
+                    piece.add_grad(grad.narrow(*axis, start, len));
+                    start += len;
+                }
+            }
+            GradientOperation::ScatterAdd(a, src, pairs) => {
+                // y = a, then y_k += src_j for every pair (j, k)
+                // a.grad = grad
+                // src_j.grad = grad_k
+                a.add_grad(grad.clone());
+                let mut partial = Tensor::zeros_like(src);
+                for &(j, k) in pairs {
+                    partial.data[j] += grad.data[k];
+                }
+                src.add_grad(partial);
+            }
+            GradientOperation::Where(condition, a, b) => {
+                // y = [ condition: a, otherwise b ]
+                // a.grad = [ condition: grad, otherwise 0 ]
+                // b.grad = [ condition: 0, otherwise grad ]
+                let a_partial = grad.zip_with(condition, |g, c| if c { g } else { T::zero() });
+                a.add_grad(a_partial.sum_to(&a.shape));
+                let b_partial = grad.zip_with(condition, |g, c| if c { T::zero() } else { g });
+                b.add_grad(b_partial.sum_to(&b.shape));
+            }
+        };
+    }
+
+    /// Inputs that gradients flow back into; constants are left out of the traversal.
+    fn parents(&self) -> Vec<Rc<RefCell<Gradient<T>>>> {
+        self.operation
+            .inputs()
+            .into_iter()
+            .filter(|input| input.requires_grad())
+            .map(|input| input.gradient.clone())
+            .collect()
+    }
+}
+
+impl<T: Element> GradientOperation<T> {
+    fn inputs(&self) -> Vec<&Tensor<T>> {
+        match self {
+            GradientOperation::None => vec![],
+            GradientOperation::Neg(a)
+            | GradientOperation::ReLU(a)
+            | GradientOperation::Pow(a, _)
+            | GradientOperation::Mean(a)
+            | GradientOperation::Exp(a)
+            | GradientOperation::Ln(a)
+            | GradientOperation::Sqrt(a)
+            | GradientOperation::Abs(a)
+            | GradientOperation::Sin(a)
+            | GradientOperation::Cos(a)
+            | GradientOperation::Powf(a, _)
+            | GradientOperation::Sigmoid(a)
+            | GradientOperation::Tanh(a)
+            | GradientOperation::GELU(a, _)
+            | GradientOperation::LeakyReLU(a, _)
+            | GradientOperation::ELU(a, _)
+            | GradientOperation::SiLU(a)
+            | GradientOperation::Softmax(a, _, _)
+            | GradientOperation::LogSoftmax(a, _, _)
+            | GradientOperation::Sum(a, _)
+            | GradientOperation::MeanAxis(a, _)
+            | GradientOperation::Max(a, _)
+            | GradientOperation::Min(a, _)
+            | GradientOperation::Var(a, _, _)
+            | GradientOperation::Reshape(a)
+            | GradientOperation::Permute(a, _)
+            | GradientOperation::Transpose(a, _, _)
+            | GradientOperation::Select(a, _) => vec![a],
+            GradientOperation::Add(a, b)
+            | GradientOperation::Sub(a, b)
+            | GradientOperation::Mul(a, b)
+            | GradientOperation::MulElem(a, b)
+            | GradientOperation::Div(a, b)
+            | GradientOperation::ScatterAdd(a, b, _)
+            | GradientOperation::Where(_, a, b) => vec![a, b],
+            GradientOperation::Cat(pieces, _) => pieces.iter().collect(),
+        }
+    }
+
+    fn into_inputs(self) -> Vec<Tensor<T>> {
+        match self {
+            GradientOperation::None => vec![],
+            GradientOperation::Neg(a)
+            | GradientOperation::ReLU(a)
+            | GradientOperation::Pow(a, _)
+            | GradientOperation::Mean(a)
+            | GradientOperation::Exp(a)
+            | GradientOperation::Ln(a)
+            | GradientOperation::Sqrt(a)
+            | GradientOperation::Abs(a)
+            | GradientOperation::Sin(a)
+            | GradientOperation::Cos(a)
+            | GradientOperation::Powf(a, _)
+            | GradientOperation::Sigmoid(a)
+            | GradientOperation::Tanh(a)
+            | GradientOperation::GELU(a, _)
+            | GradientOperation::LeakyReLU(a, _)
+            | GradientOperation::ELU(a, _)
+            | GradientOperation::SiLU(a)
+            | GradientOperation::Softmax(a, _, _)
+            | GradientOperation::LogSoftmax(a, _, _)
+            | GradientOperation::Sum(a, _)
+            | GradientOperation::MeanAxis(a, _)
+            | GradientOperation::Max(a, _)
+            | GradientOperation::Min(a, _)
+            | GradientOperation::Var(a, _, _)
+            | GradientOperation::Reshape(a)
+            | GradientOperation::Permute(a, _)
+            | GradientOperation::Transpose(a, _, _)
+            | GradientOperation::Select(a, _) => vec![a],
+            GradientOperation::Add(a, b)
+            | GradientOperation::Sub(a, b)
+            | GradientOperation::Mul(a, b)
+            | GradientOperation::MulElem(a, b)
+            | GradientOperation::Div(a, b)
+            | GradientOperation::ScatterAdd(a, b, _)
+            | GradientOperation::Where(_, a, b) => vec![a, b],
+            GradientOperation::Cat(pieces, _) => pieces,
+        }
+    }
+}
+
+impl<T: Element> Drop for Gradient<T> {
+    // The default drop recurses once per node, which overflows the stack on long graphs.
+    // Instead, detach the inputs of every node we are the last owner of and drop them in a loop.
+    fn drop(&mut self) {
+        let mut pending =
+            std::mem::replace(&mut self.operation, GradientOperation::None).into_inputs();
+        pending.extend(self.value.take());
+        while let Some(tensor) = pending.pop() {
+            if let Ok(cell) = Rc::try_unwrap(tensor.gradient) {
+                let mut gradient = cell.into_inner();
+                pending.extend(
+                    std::mem::replace(&mut gradient.operation, GradientOperation::None)
+                        .into_inputs(),
+                );
+                pending.extend(gradient.value.take());
+            }
+        }
+    }
+}
+
+/// Nodes reachable from `root` in post-order, so each node comes after all of its inputs.
+/// Uses an explicit stack so deep graphs can't overflow the call stack.
+fn topological_order<T: Float>(root: &Rc<RefCell<Gradient<T>>>) -> Vec<Rc<RefCell<Gradient<T>>>> {
+    let mut order = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![(root.clone(), false)];
+    while let Some((node, expanded)) = stack.pop() {
+        if expanded {
+            order.push(node);
+            continue;
+        }
+        if !visited.insert(Rc::as_ptr(&node)) {
+            continue;
+        }
+        let parents = node.borrow().parents();
+        stack.push((node, true));
+        stack.extend(
+            parents
+                .into_iter()
+                .filter(|parent| !visited.contains(&Rc::as_ptr(parent)))
+                .map(|parent| (parent, false)),
+        );
+    }
+    order
+}
+
+impl<T: Float> Tensor<T> {
+    /// Elements of `a` where `condition` holds and of `b` elsewhere, broadcasting all
+    /// three. Differentiable with respect to `a` and `b`; `condition` is treated as a constant.
+    pub fn where_(condition: &Tensor<bool>, a: &Tensor<T>, b: &Tensor<T>) -> Tensor<T> {
+        let shape = broadcast_shape(&broadcast_shape(&condition.shape, &a.shape), &b.shape);
+        let condition = condition.broadcast_to(&shape);
+        let (left, right) = (a.broadcast_to(&shape), b.broadcast_to(&shape));
+        let data = (0..condition.data.len())
+            .map(|i| match condition.data[i] {
+                true => left.data[i],
+                false => right.data[i],
+            })
+            .collect();
+
+        Tensor::from_operation(
+            format!(
+                "Where({}, {}, {})",
+                format_name(&condition),
+                format_name(a),
+                format_name(b)
+            ),
+            &shape,
+            data,
+            GradientOperation::Where(condition, a.clone(), b.clone()),
+        )
+    }
+
+    /// Joins `tensors` along `axis`. They must agree on every other axis.
+    pub fn cat(tensors: &[Tensor<T>], axis: usize) -> Tensor<T> {
+        assert!(!tensors.is_empty(), "Cannot concatenate no tensors");
+        let first = &tensors[0];
+        assert!(
+            axis < first.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            first.shape
+        );
+        let mut shape = first.shape.clone();
+        shape[axis] = 0;
+        for tensor in tensors {
+            let mut expected = first.shape.clone();
+            expected[axis] = tensor.shape.get(axis).copied().unwrap_or(0);
+            assert_eq!(
+                expected, tensor.shape,
+                "Cannot concatenate {:?} with {:?} along axis {}",
+                first.shape, tensor.shape, axis
+            );
+            shape[axis] += tensor.shape[axis];
+        }
+
+        // Every tensor is contiguous, so each contributes one block per index of the outer axes
+        let outer: usize = shape[..axis].iter().product();
+        let mut data = Vec::with_capacity(shape.iter().product());
+        for i in 0..outer {
+            for tensor in tensors {
+                let block = tensor.data.len() / outer;
+                data.extend_from_slice(&tensor.data[i * block..(i + 1) * block]);
+            }
+        }
+
+        let names: Vec<String> = tensors.iter().map(format_name).collect();
+        Tensor::from_operation(
+            format!("Cat_{}({})", axis, names.join(", ")),
+            &shape,
+            data.into(),
+            GradientOperation::Cat(tensors.to_vec(), axis),
+        )
+    }
+
+    /// Joins `tensors`, which must all have the same shape, along a new axis inserted before
+    /// `axis`.
+    pub fn stack(tensors: &[Tensor<T>], axis: usize) -> Tensor<T> {
+        if let Some(first) = tensors.first() {
+            assert!(
+                axis <= first.rank(),
+                "Axis {} out of range for shape {:?}",
+                axis,
+                first.shape
+            );
+        }
+        let unsqueezed: Vec<Tensor<T>> = tensors.iter().map(|t| t.unsqueeze(axis)).collect();
+        Tensor::cat(&unsqueezed, axis)
+    }
+
+    /// Builds the output of `operation`, recording it in the graph if any input requires grad.
+    /// Otherwise (or under `no_grad`) the result is a plain tensor and `operation` is discarded.
+    fn from_operation(
+        name: String,
+        shape: &[usize],
+        data: Storage<T>,
+        operation: GradientOperation<T>,
+    ) -> Tensor<T> {
+        let requires_grad =
+            is_grad_enabled() && operation.inputs().iter().any(|input| input.requires_grad());
+        if !requires_grad {
+            return Tensor::from_storage(shape, data).named(name);
+        }
+        Tensor {
+            name,
+            gradient: Gradient {
+                operation,
+                value: None,
+                requires_grad,
+            }
+            .wrap(),
+            ..Tensor::from_storage(shape, data)
+        }
+    }
+
+    /// Applies `fun` to every element and records the result as `operation`.
+    fn unary_operation(
+        &self,
+        label: &str,
+        fun: impl Fn(T) -> T,
+        operation: GradientOperation<T>,
+    ) -> Tensor<T> {
+        Tensor::from_operation(
+            unary_label(label.to_string(), self),
+            &self.shape,
+            self.map(fun).data,
+            operation,
+        )
+    }
+
+    /// `zip_with(right, fun)`, using the vectorized `kernel` when no broadcasting is needed.
+    fn zip_elementwise(
+        &self,
+        right: &Tensor<T>,
+        kernel: fn(&[T], &[T]) -> Vec<T>,
+        fun: impl Fn(T, T) -> T,
+    ) -> Tensor<T> {
+        match self.shape == right.shape {
+            true => Tensor::from_shape(&self.shape, kernel(&self.data, &right.data)),
+            false => self.zip_with(right, fun),
+        }
+    }
+
+    /// Offsets into `self` of the elements chosen by `indices` (as from `argmax`), one per lane.
+    fn select_lanes(&self, axis: usize, indices: Tensor<i64>) -> Vec<usize> {
+        self.lanes(axis)
+            .into_iter()
+            .zip(indices.data.iter())
+            .map(|(start, &i)| start + (i as usize) * self.strides[axis])
+            .collect()
+    }
+
+    /// Repeats a gradient of a reduction over `axis` (with or without the kept dimension)
+    /// back across that axis, giving it `shape`.
+    fn unreduce(&self, shape: &[usize], axis: usize) -> Tensor<T> {
+        let mut kept = shape.to_vec();
+        kept[axis] = 1;
+        Tensor::from_storage(&kept, self.data.clone()).broadcast_to(shape)
+    }
+
+    /// Selects entries `indices` of `axis`, recording where every output element came from.
+    fn select_along(&self, axis: usize, indices: &[usize], label: String) -> Tensor<T> {
+        assert!(
+            axis < self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        for &i in indices {
+            assert!(
+                i < self.shape[axis],
+                "Index {} is out of bounds for axis {} of {:?}",
+                i,
+                axis,
+                self.shape
+            );
+        }
+        let mut shape = self.shape.clone();
+        shape[axis] = indices.len();
+        let selected: Vec<usize> = (0..shape.iter().product())
+            .map(|position| {
+                let mut index = unravel(&shape, position);
+                index[axis] = indices[index[axis]];
+                self.offset(&index)
+            })
+            .collect();
+
+        Tensor::from_operation(
+            format!("{}({})", label, format_name(self)),
+            &shape,
+            selected.iter().map(|&offset| self.data[offset]).collect(),
+            GradientOperation::Select(self.clone(), selected),
+        )
+    }
+
+    /// Offsets into `self` addressed by every element of `index`, in order: its own position,
+    /// except along `axis`, where its value is used instead.
+    fn index_offsets(&self, axis: usize, index: &Tensor<i64>) -> Vec<usize> {
+        assert!(
+            index.rank() == self.rank()
+                && index
+                    .shape
+                    .iter()
+                    .zip(self.shape.iter())
+                    .enumerate()
+                    .all(|(d, (i, s))| d == axis || i <= s),
+            "Index of shape {:?} doesn't fit in {:?} along axis {}",
+            index.shape,
+            self.shape,
+            axis
+        );
+        (0..index.data.len())
+            .map(|position| {
+                let value = index.data[position];
+                assert!(
+                    value >= 0 && (value as usize) < self.shape[axis],
+                    "Index {} is out of bounds for axis {} of {:?}",
+                    value,
+                    axis,
+                    self.shape
+                );
+                let mut target = index.unravel(position);
+                target[axis] = value as usize;
+                self.offset(&target)
+            })
+            .collect()
+    }
+
+    /// Contiguous copy of `self` with its axes reordered by `axes`.
+    fn permuted(&self, axes: &[usize]) -> Tensor<T> {
+        let mut seen = vec![false; self.rank()];
+        for &axis in axes {
+            assert!(
+                axis < self.rank() && !seen[axis],
+                "{:?} is not a permutation of the axes of {:?}",
+                axes,
+                self.shape
+            );
+            seen[axis] = true;
+        }
+        assert_eq!(
+            axes.len(),
+            self.rank(),
+            "{:?} is not a permutation of the axes of {:?}",
+            axes,
+            self.shape
+        );
+
+        let shape: Vec<usize> = axes.iter().map(|&axis| self.shape[axis]).collect();
+        let data = (0..self.data.len())
+            .map(|position| {
+                let index = unravel(&shape, position);
+                let offset: usize = (0..axes.len())
+                    .map(|i| index[i] * self.strides[axes[i]])
+                    .sum();
+                self.data[offset]
+            })
+            .collect();
+        Tensor::from_shape(&shape, data)
+    }
+}
+
 #[derive(Clone)]
 pub enum Parents {
     None,
@@ -56,485 +771,896 @@ pub enum Parents {
 
 // TODO: no words
 #[derive(Debug, Clone)]
-pub enum GradientOperation {
+pub enum GradientOperation<T: Element = f64> {
     None,
-    Neg(Tensor),
-    ReLU(Tensor),
-    Pow(Tensor, i32),
-    Mean(Tensor),
-    Add(Tensor, Tensor),
-    Sub(Tensor, Tensor),
-    Mul(Tensor, Tensor),
+    Neg(Tensor<T>),
+    ReLU(Tensor<T>),
+    Pow(Tensor<T>, i32),
+    Mean(Tensor<T>),
+    Add(Tensor<T>, Tensor<T>),
+    Sub(Tensor<T>, Tensor<T>),
+    Mul(Tensor<T>, Tensor<T>),
+    MulElem(Tensor<T>, Tensor<T>),
+    Div(Tensor<T>, Tensor<T>),
+    Where(Tensor<bool>, Tensor<T>, Tensor<T>),
+    ScatterAdd(Tensor<T>, Tensor<T>, Vec<(usize, usize)>),
+    Cat(Vec<Tensor<T>>, usize),
+    Select(Tensor<T>, Vec<usize>),
+    Transpose(Tensor<T>, usize, usize),
+    Reshape(Tensor<T>),
+    Permute(Tensor<T>, Vec<usize>),
+    Sum(Tensor<T>, usize),
+    MeanAxis(Tensor<T>, usize),
+    Max(Tensor<T>, Vec<usize>),
+    Min(Tensor<T>, Vec<usize>),
+    Var(Tensor<T>, usize, bool),
+    /// The input, the axis and the output, which the backward rule is written in terms of.
+    Softmax(Tensor<T>, usize, Tensor<T>),
+    LogSoftmax(Tensor<T>, usize, Tensor<T>),
+    Sigmoid(Tensor<T>),
+    Tanh(Tensor<T>),
+    GELU(Tensor<T>, GeluApproximation),
+    LeakyReLU(Tensor<T>, T),
+    ELU(Tensor<T>, T),
+    SiLU(Tensor<T>),
+    Exp(Tensor<T>),
+    Ln(Tensor<T>),
+    Sqrt(Tensor<T>),
+    Abs(Tensor<T>),
+    Sin(Tensor<T>),
+    Cos(Tensor<T>),
+    Powf(Tensor<T>, T),
 }
 
-pub trait Differentiable {
-    fn grad(&self) -> Tensor;
+pub trait Differentiable: Sized {
+    fn grad(&self) -> Self;
     fn with_grad(self) -> Self;
-    fn set_grad(&self, grad: Tensor);
+    fn set_grad(&self, grad: Self);
     fn reset_grad(&self);
-    fn add_grad(&self, grad: Tensor);
+    fn add_grad(&self, grad: Self);
     fn has_grad(&self) -> bool;
+    fn requires_grad(&self) -> bool;
+    fn detach(&self) -> Self;
 
-    fn last(&self) -> Tensor;
+    fn last(&self) -> Self;
 
     fn backward(&self);
 
     // TODO: move these elsewhere
-    fn relu(&self) -> Tensor;
-    fn mean(&self) -> Tensor;
-    fn pow(&self, exp: i32) -> Tensor;
+    fn relu(&self) -> Self;
+    fn mean(&self) -> Self;
+    fn pow(&self, exp: i32) -> Self;
+    fn mul_elem(&self, right: &Self) -> Self;
+    fn div(&self, right: &Self) -> Self;
+    fn matmul(&self, right: &Self) -> Self;
+    fn exp(&self) -> Self;
+    fn ln(&self) -> Self;
+    fn sqrt(&self) -> Self;
+    fn abs(&self) -> Self;
+    fn sin(&self) -> Self;
+    fn cos(&self) -> Self;
+    fn powf(&self, exp: f64) -> Self;
+    fn sigmoid(&self) -> Self;
+    fn tanh(&self) -> Self;
+    fn gelu(&self, approximation: GeluApproximation) -> Self;
+    fn leaky_relu(&self, slope: f64) -> Self;
+    fn elu(&self, alpha: f64) -> Self;
+    fn silu(&self) -> Self;
+    fn softmax(&self, axis: usize) -> Self;
+    fn log_softmax(&self, axis: usize) -> Self;
+    fn sum(&self, axis: usize, keepdim: bool) -> Self;
+    fn mean_axis(&self, axis: usize, keepdim: bool) -> Self;
+    fn max(&self, axis: usize, keepdim: bool) -> Self;
+    fn min(&self, axis: usize, keepdim: bool) -> Self;
+    fn var(&self, axis: usize, unbiased: bool, keepdim: bool) -> Self;
+    fn std(&self, axis: usize, unbiased: bool, keepdim: bool) -> Self;
+    fn reshape(&self, shape: &[usize]) -> Self;
+    fn flatten(&self, start: usize, end: usize) -> Self;
+    fn squeeze(&self, axis: usize) -> Self;
+    fn unsqueeze(&self, axis: usize) -> Self;
+    fn permute(&self, axes: &[usize]) -> Self;
+    fn transpose(&self, first: usize, second: usize) -> Self;
+    fn narrow(&self, axis: usize, start: usize, len: usize) -> Self;
+    fn index_select(&self, axis: usize, indices: &[usize]) -> Self;
+    fn masked_select(&self, mask: &Tensor<bool>) -> Self;
+    fn split(&self, sizes: &[usize], axis: usize) -> Vec<Self>;
+    fn chunk(&self, n: usize, axis: usize) -> Vec<Self>;
+    fn gather(&self, axis: usize, index: &Tensor<i64>) -> Self;
+    fn scatter_add(&self, axis: usize, index: &Tensor<i64>, src: &Self) -> Self;
+    fn masked_fill(&self, mask: &Tensor<bool>, value: f64) -> Self;
 }
 
-impl Differentiable for Tensor {
+impl<T: Float> Differentiable for Tensor<T> {
     fn with_grad(self) -> Self {
         let mut gradient = self.gradient.borrow_mut();
-        match gradient.value {
-            Some(_) => println!("Tensor already has grad enabled."),
-            None => {
-                let (m, n) = self.size;
-                gradient.value = Some(Tensor::zeros(m, n));
-                gradient.last = Some(Tensor::from_vector(self.data.clone()))
+        match gradient.requires_grad {
+            true => println!("Tensor already has grad enabled."),
+            false => {
+                gradient.requires_grad = true;
+                gradient.value = Some(Tensor::zeros_like(&self));
             }
         };
         self.clone() // TODO: is this bad?
     }
-    fn grad(&self) -> Tensor {
+    fn grad(&self) -> Tensor<T> {
         let gradient = self.gradient.borrow();
-        let value = gradient
-            .value
-            .clone()
-            .expect("Tensor doesn't have grad enabled");
-        value.clone()
+        match &gradient.value {
+            Some(value) => value.clone(),
+            None if gradient.requires_grad => Tensor::zeros_like(self),
+            None => panic!("Tensor doesn't have grad enabled"),
+        }
     }
 
     fn reset_grad(&self) {
-        let (w, h) = self.size;
-        self.set_grad(Tensor::zeros(w, h));
+        self.set_grad(Tensor::zeros_like(self));
     }
 
-    fn set_grad(&self, grad: Tensor) {
+    fn set_grad(&self, grad: Tensor<T>) {
         let mut gradient = self.gradient.borrow_mut();
         gradient.value = Some(grad)
     }
 
-    fn add_grad(&self, grad: Tensor) {
+    /// Accumulates `grad` into this tensor's grad. Tensors that don't require grad are
+    /// constants as far as autograd is concerned, so gradients reaching them are dropped.
+    fn add_grad(&self, grad: Tensor<T>) {
         let mut gradient = self.gradient.borrow_mut();
-        if let Some(value) = gradient.value.clone() {
-            gradient.value = Some(value + grad);
+        if !gradient.requires_grad {
+            return;
         }
-        // TODO: should this fail silently? I think so, b/c should do nothing with tensors w/o grad
-        // enabled, right?
-        // What if grad isn't enabled but gradients flow through this node?
+        gradient.value = match &gradient.value {
+            Some(value) => Some(value.zip_with(&grad, |x, y| x + y)),
+            None => Some(Tensor::from_storage(&grad.shape, grad.data)),
+        };
     }
 
+    /// Whether a grad buffer has been allocated, either by `with_grad` or by `backward`.
     fn has_grad(&self) -> bool {
         let gradient = self.gradient.borrow();
         gradient.value.is_some()
     }
 
-    fn last(&self) -> Tensor {
-        let gradient = self.gradient.borrow();
-        match &gradient.last {
-            Some(value) => value.clone(),
-            None => self.clone(),
-        }
+    fn requires_grad(&self) -> bool {
+        self.gradient.borrow().requires_grad
+    }
+
+    /// The same values as a fresh leaf that is cut from the graph and doesn't require grad.
+    fn detach(&self) -> Tensor<T> {
+        Tensor::from_storage(&self.shape, self.data.clone()).named(self.name.clone())
+    }
+
+    /// The value this tensor had when an operation used it. The operand handle the graph holds
+    /// is that value, as in-place writes copy a tensor's storage away from any shared handles.
+    fn last(&self) -> Tensor<T> {
+        self.clone()
     }
 
     fn backward(&self) {
-        if !self.has_grad() {
+        if !self.requires_grad() {
             return;
         }
-        let grad = self.grad();
-        let gradient = self.gradient.borrow();
-        let g_debug = gradient.clone();
-        println!(
-            "BACKWARD: {:?} \t\t = {}, grad = {}",
-            g_debug.operation,
-            g_debug.last.unwrap(),
-            g_debug.value.unwrap()
-        );
-        match &gradient.operation {
-            GradientOperation::None => {}
-            GradientOperation::Neg(a) => {
-                // y = -a
-                // a.grad = dL/da = (dL/dy)(dy/da) = grad * -1
-                a.add_grad(-grad.clone());
-                a.backward();
-                // println!("{}: {}", a.name, a.grad());
-            }
-            GradientOperation::Add(a, b) => {
-                // y = a + b
-                // a.grad = dL/da = (dL/dy)(dy/da) = grad * 1
-                // b.grad = dL/db = (dL/dy)(dy/db) = grad * 1
-                a.add_grad(grad.clone());
-                b.add_grad(grad.clone());
-                a.backward();
-                b.backward();
-                // println!("{}: {}, {}: {}", a.name, a.grad(), b.name, b.grad());
-            }
-            GradientOperation::Sub(a, b) => {
-                // y = a - b
-                // a.grad = dL/da = (dL/dy)(dy/da) = grad * 1
-                // b.grad = dL/db = (dL/dy)(dy/db) = grad * -1
-                a.add_grad(grad.clone());
-                b.add_grad(-grad.clone());
-                a.backward();
-                b.backward();
-                // println!("{}: {}, {}: {}", a.name, a.grad(), b.name, b.grad());
-            }
-            GradientOperation::Mul(a, b) => {
-                // y = a * b
-                // a.grad = dL/da = (dL/dy)(dy/da) = grad * b
-                // b.grad = dL/db = (dL/dy)(dy/db) = grad * a
-                //
-                // Y    [m x p] = A.B = [m x n].[n x p]
-                // grad [m x p]
-                // A.grad [m x n] = grad.(B^T) = [m x p].[p x n]
-                // B.grad [n x p] = (A^T).grad = [n x m].[m x p]
-                let a_last = a.last();
-                let b_last = b.last();
-                let (a1, a2) = a_last.size;
-                let (b1, b2) = b_last.size;
-                println!("a_size: {}x{}, b_size: {}x{}", a1, a2, b1, b2);
-                let (g1, g2) = grad.size;
-                println!("grad size: {}x{}", g1, g2);
-                let a_partial = grad.clone() * b_last.transpose();
-                println!("a_partial: {}", a_partial.clone());
-                a.add_grad(a_partial);
-                let b_partial = a_last.transpose() * grad.clone();
-                println!("b_partial: {}", b_partial.clone());
-                b.add_grad(b_partial);
-                a.backward();
-                b.backward();
-            }
-            GradientOperation::ReLU(a) => {
-                // y = [ x >= 0: x, x < 0: 0 ]
-                // dy/dx = [x >= 0: 1, x < 0: 0]
-                let a_last = a.last();
-                a.add_grad(
-                    a_last.apply(|i, j, last| if last[i][j] >= 0.0 { grad[i][j] } else { 0.0 }),
-                );
-                // println!("{}: {}", a.name, a.grad());
-                a.backward();
+        // Every node's grad must be complete before it is pushed to its inputs, so visit
+        // consumers before producers: reverse post-order of the graph below `self`.
+        // The partials themselves are plain values and shouldn't grow the graph.
+        let _guard = NoGradGuard::new();
+        let order = topological_order(&self.gradient);
+        // Only leaves accumulate across calls. Grads of operation outputs below `self` belong to
+        // one pass, or a second pass through a shared node would push the first one's again.
+        for node in order.iter().filter(|node| !Rc::ptr_eq(node, &self.gradient)) {
+            let mut node = node.borrow_mut();
+            if !matches!(node.operation, GradientOperation::None) {
+                node.value = None;
             }
-            GradientOperation::Pow(a, b) => {
-                // y = a^b
-                // dy/da = ba^(b-1)
-                let a_last = a.last();
-                a.add_grad(
-                    a_last.apply(|i, j, last| (*b as f64) * last[i][j].powf((b - 1) as f64)),
-                );
-                // println!("{}: {}, b: {}", a.name, a.grad(), b);
-                a.backward();
-            }
-            GradientOperation::Mean(a) => {
-                // y = mean(a)
-                // dy/da = ba^(b-1)
-                let a_last = a.last();
-                let denominator = a_last.num_elements() as f64;
-                a.add_grad(a_last.apply(|i, j, last| last[i][j] / denominator));
-                // println!("{}: {}", a.name, a.grad());
-                a.backward();
-            }
-        };
+        }
+        for node in order.iter().rev() {
+            node.borrow().propagate();
+        }
     }
 
-    fn relu(&self) -> Tensor {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
-        (0..m).for_each(|i| {
-            (0..n).for_each(|j| {
-                data[i][j] = match self.data[i][j] {
-                    x if x >= 0.0 => x,
-                    _ => 0.0,
-                }
+    fn relu(&self) -> Tensor<T> {
+        let data = simd::relu(&self.data);
+
+        Tensor::from_operation(
+            unary_label("ReLU".to_string(), self),
+            &self.shape,
+            data.into(),
+            GradientOperation::ReLU(self.clone()),
+        )
+    }
+
+    fn mean(&self) -> Tensor<T> {
+        let sum: T = self.data.iter().copied().sum();
+        let data = vec![sum / T::from_f64(self.num_elements() as f64)];
+
+        Tensor::from_operation(
+            unary_label("Mean".to_string(), self),
+            &[1, 1],
+            data.into(),
+            GradientOperation::Mean(self.clone()),
+        )
+    }
+
+    fn pow(&self, exp: i32) -> Tensor<T> {
+        let data = simd::powi(&self.data, exp);
+
+        Tensor::from_operation(
+            format!("({}^{})", format_name(self), exp),
+            &self.shape,
+            data.into(),
+            GradientOperation::Pow(self.clone(), exp),
+        )
+    }
+
+    /// Elementwise (Hadamard) product, broadcasting like `+`.
+    fn mul_elem(&self, right: &Tensor<T>) -> Tensor<T> {
+        let result = self.zip_with(right, |x, y| x * y);
+
+        Tensor::from_operation(
+            binary_label(self, "⊙".to_string(), right),
+            &result.shape,
+            result.data,
+            GradientOperation::MulElem(self.clone(), right.clone()),
+        )
+    }
+
+    /// Elementwise division, broadcasting like `+`.
+    fn div(&self, right: &Tensor<T>) -> Tensor<T> {
+        let result = self.zip_with(right, |x, y| x / y);
+
+        Tensor::from_operation(
+            binary_label(self, "/".to_string(), right),
+            &result.shape,
+            result.data,
+            GradientOperation::Div(self.clone(), right.clone()),
+        )
+    }
+
+    fn exp(&self) -> Tensor<T> {
+        self.unary_operation("exp", T::exp, GradientOperation::Exp(self.clone()))
+    }
+
+    fn ln(&self) -> Tensor<T> {
+        self.unary_operation("ln", T::ln, GradientOperation::Ln(self.clone()))
+    }
+
+    fn sqrt(&self) -> Tensor<T> {
+        self.unary_operation("sqrt", T::sqrt, GradientOperation::Sqrt(self.clone()))
+    }
+
+    fn abs(&self) -> Tensor<T> {
+        self.unary_operation("abs", T::abs, GradientOperation::Abs(self.clone()))
+    }
+
+    fn sin(&self) -> Tensor<T> {
+        self.unary_operation("sin", T::sin, GradientOperation::Sin(self.clone()))
+    }
+
+    fn cos(&self) -> Tensor<T> {
+        self.unary_operation("cos", T::cos, GradientOperation::Cos(self.clone()))
+    }
+
+    fn powf(&self, exp: f64) -> Tensor<T> {
+        let exp = T::from_f64(exp);
+        Tensor::from_operation(
+            format!("({}^{})", format_name(self), exp),
+            &self.shape,
+            self.map(|x| x.powf(exp)).data,
+            GradientOperation::Powf(self.clone(), exp),
+        )
+    }
+
+    fn sigmoid(&self) -> Tensor<T> {
+        self.unary_operation(
+            "Sigmoid",
+            activation::sigmoid,
+            GradientOperation::Sigmoid(self.clone()),
+        )
+    }
+
+    fn tanh(&self) -> Tensor<T> {
+        self.unary_operation("Tanh", T::tanh, GradientOperation::Tanh(self.clone()))
+    }
+
+    fn gelu(&self, approximation: GeluApproximation) -> Tensor<T> {
+        self.unary_operation(
+            "GELU",
+            |x| activation::gelu(x, approximation),
+            GradientOperation::GELU(self.clone(), approximation),
+        )
+    }
+
+    fn leaky_relu(&self, slope: f64) -> Tensor<T> {
+        self.unary_operation(
+            "LeakyReLU",
+            |x| activation::leaky_relu(x, T::from_f64(slope)),
+            GradientOperation::LeakyReLU(self.clone(), T::from_f64(slope)),
+        )
+    }
+
+    fn elu(&self, alpha: f64) -> Tensor<T> {
+        self.unary_operation(
+            "ELU",
+            |x| activation::elu(x, T::from_f64(alpha)),
+            GradientOperation::ELU(self.clone(), T::from_f64(alpha)),
+        )
+    }
+
+    fn silu(&self) -> Tensor<T> {
+        self.unary_operation(
+            "SiLU",
+            activation::silu,
+            GradientOperation::SiLU(self.clone()),
+        )
+    }
+
+    /// Normalizes each lane along `axis` to a probability distribution. The lane maximum is
+    /// subtracted before exponentiating so large inputs don't overflow.
+    fn softmax(&self, axis: usize) -> Tensor<T> {
+        let data = self
+            .map_lanes(axis, |lane| {
+                let max = lane.iter().copied().fold(T::neg_infinity(), T::max);
+                let exps: Vec<T> = lane.iter().map(|&x| (x - max).exp()).collect();
+                let sum: T = exps.iter().copied().sum();
+                exps.iter().map(|&e| e / sum).collect()
             })
+            .data;
+
+        let output = Tensor::from_storage(&self.shape, data.clone());
+        Tensor::from_operation(
+            format!("Softmax_{}({})", axis, format_name(self)),
+            &self.shape,
+            data,
+            GradientOperation::Softmax(self.clone(), axis, output),
+        )
+    }
+
+    /// `ln(softmax(axis))`, computed as `x - max - ln(sum(e^(x - max)))` for stability.
+    fn log_softmax(&self, axis: usize) -> Tensor<T> {
+        let data = self
+            .map_lanes(axis, |lane| {
+                let max = lane.iter().copied().fold(T::neg_infinity(), T::max);
+                let log_sum = lane.iter().map(|&x| (x - max).exp()).sum::<T>().ln();
+                lane.iter().map(|&x| x - max - log_sum).collect()
+            })
+            .data;
+
+        let output = Tensor::from_storage(&self.shape, data.clone());
+        Tensor::from_operation(
+            format!("LogSoftmax_{}({})", axis, format_name(self)),
+            &self.shape,
+            data,
+            GradientOperation::LogSoftmax(self.clone(), axis, output),
+        )
+    }
+
+    fn sum(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        let result = self.reduce_lanes(axis, keepdim, |lane| lane.iter().copied().sum());
+
+        Tensor::from_operation(
+            format!("Sum_{}({})", axis, format_name(self)),
+            &result.shape,
+            result.data,
+            GradientOperation::Sum(self.clone(), axis),
+        )
+    }
+
+    fn mean_axis(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        let result = self.reduce_lanes(axis, keepdim, |lane| {
+            lane.iter().copied().sum::<T>() / T::from_f64(lane.len() as f64)
         });
 
-        Tensor {
-            name: unary_label("ReLU".to_string(), self),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                last: Some(Tensor::from_vector(data)),
-                operation: GradientOperation::ReLU(self.clone()),
-                value: Some(Tensor::fill(m, n, 0.0)),
-            }
-            .wrap(),
-        }
+        Tensor::from_operation(
+            format!("Mean_{}({})", axis, format_name(self)),
+            &result.shape,
+            result.data,
+            GradientOperation::MeanAxis(self.clone(), axis),
+        )
     }
 
-    fn mean(&self) -> Tensor {
-        let (m, n) = self.size;
-        let mut sum = 0.0;
-        (0..m).for_each(|i| (0..n).for_each(|j| sum += self.data[i][j]));
-        let data = vec![vec![sum / (self.num_elements() as f64)]];
+    /// Largest element of each lane along `axis`. The gradient flows only to that element.
+    fn max(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        self.assert_nonempty_axis("max", axis);
+        let selected = self.select_lanes(axis, self.argmax(axis, keepdim));
 
-        Tensor {
-            name: unary_label("Mean".to_string(), self),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                last: Some(Tensor::from_vector(data)),
-                operation: GradientOperation::Mean(self.clone()),
-                value: Some(Tensor::fill(m, n, 0.0)),
-            }
-            .wrap(),
+        Tensor::from_operation(
+            format!("Max_{}({})", axis, format_name(self)),
+            &self.reduced_shape(axis, keepdim),
+            selected.iter().map(|&offset| self.data[offset]).collect(),
+            GradientOperation::Max(self.clone(), selected),
+        )
+    }
+
+    /// Smallest element of each lane along `axis`. The gradient flows only to that element.
+    fn min(&self, axis: usize, keepdim: bool) -> Tensor<T> {
+        self.assert_nonempty_axis("min", axis);
+        let selected = self.select_lanes(axis, self.argmin(axis, keepdim));
+
+        Tensor::from_operation(
+            format!("Min_{}({})", axis, format_name(self)),
+            &self.reduced_shape(axis, keepdim),
+            selected.iter().map(|&offset| self.data[offset]).collect(),
+            GradientOperation::Min(self.clone(), selected),
+        )
+    }
+
+    /// Variance of each lane along `axis`, dividing by `n - 1` if `unbiased`, else by `n`.
+    fn var(&self, axis: usize, unbiased: bool, keepdim: bool) -> Tensor<T> {
+        let ddof = if unbiased { 1.0 } else { 0.0 };
+        let result = self.reduce_lanes(axis, keepdim, |lane| {
+            let n = lane.len() as f64;
+            let mean = lane.iter().copied().sum::<T>() / T::from_f64(n);
+            lane.iter().map(|&x| (x - mean).powi(2)).sum::<T>() / T::from_f64(n - ddof)
+        });
+
+        Tensor::from_operation(
+            format!("Var_{}({})", axis, format_name(self)),
+            &result.shape,
+            result.data,
+            GradientOperation::Var(self.clone(), axis, unbiased),
+        )
+    }
+
+    fn std(&self, axis: usize, unbiased: bool, keepdim: bool) -> Tensor<T> {
+        self.var(axis, unbiased, keepdim).sqrt()
+    }
+
+    /// Same elements in the same row-major order, with a new shape of the same size.
+    fn reshape(&self, shape: &[usize]) -> Tensor<T> {
+        assert_eq!(
+            shape.iter().product::<usize>(),
+            self.data.len(),
+            "Cannot reshape {:?} into {:?}",
+            self.shape,
+            shape
+        );
+
+        Tensor::from_operation(
+            format!("Reshape({})", format_name(self)),
+            shape,
+            self.data.clone(),
+            GradientOperation::Reshape(self.clone()),
+        )
+    }
+
+    /// Merges axes `start..=end` into one.
+    fn flatten(&self, start: usize, end: usize) -> Tensor<T> {
+        assert!(
+            start <= end && end < self.rank(),
+            "Cannot flatten axes {}..={} of {:?}",
+            start,
+            end,
+            self.shape
+        );
+        let mut shape = self.shape[..start].to_vec();
+        shape.push(self.shape[start..=end].iter().product());
+        shape.extend_from_slice(&self.shape[end + 1..]);
+        self.reshape(&shape)
+    }
+
+    /// Removes `axis`, which must have size 1.
+    fn squeeze(&self, axis: usize) -> Tensor<T> {
+        assert!(
+            axis < self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        assert_eq!(
+            self.shape[axis], 1,
+            "Cannot squeeze axis {} of {:?}",
+            axis, self.shape
+        );
+        let mut shape = self.shape.clone();
+        shape.remove(axis);
+        self.reshape(&shape)
+    }
+
+    /// Inserts an axis of size 1 before `axis`.
+    fn unsqueeze(&self, axis: usize) -> Tensor<T> {
+        assert!(
+            axis <= self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        let mut shape = self.shape.clone();
+        shape.insert(axis, 1);
+        self.reshape(&shape)
+    }
+
+    /// Reorders the axes, so that axis `i` of the result is axis `axes[i]` of `self`.
+    fn permute(&self, axes: &[usize]) -> Tensor<T> {
+        let result = self.permuted(axes);
+
+        Tensor::from_operation(
+            format!("Permute{:?}({})", axes, format_name(self)),
+            &result.shape,
+            result.data,
+            GradientOperation::Permute(self.clone(), axes.to_vec()),
+        )
+    }
+
+    /// Swaps axes `first` and `second`; `transpose(0, 1)` is the matrix transpose.
+    fn transpose(&self, first: usize, second: usize) -> Tensor<T> {
+        for axis in [first, second] {
+            assert!(
+                axis < self.rank(),
+                "Axis {} out of range for shape {:?}",
+                axis,
+                self.shape
+            );
         }
+        let result = self.permuted(&swapped_axes(self.rank(), first, second));
+
+        Tensor::from_operation(
+            format!("Transpose_{}_{}({})", first, second, format_name(self)),
+            &result.shape,
+            result.data,
+            GradientOperation::Transpose(self.clone(), first, second),
+        )
+    }
+
+    /// The `len` entries of `axis` starting at `start`.
+    fn narrow(&self, axis: usize, start: usize, len: usize) -> Tensor<T> {
+        let indices: Vec<usize> = (start..start + len).collect();
+        self.select_along(
+            axis,
+            &indices,
+            format!("Narrow_{}_{}..{}", axis, start, start + len),
+        )
+    }
+
+    /// The entries of `axis` at `indices`, in that order. Indices may repeat.
+    fn index_select(&self, axis: usize, indices: &[usize]) -> Tensor<T> {
+        self.select_along(axis, indices, format!("IndexSelect_{}{:?}", axis, indices))
     }
 
-    fn pow(&self, exp: i32) -> Tensor {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
-        (0..m).for_each(|i| {
-            (0..n).for_each(|j| {
-                data[i][j] = self.data[i][j].powi(exp);
+    /// Rank-1 tensor of the elements, in row-major order, where the broadcast `mask` holds.
+    fn masked_select(&self, mask: &Tensor<bool>) -> Tensor<T> {
+        let mask = mask.broadcast_to(&self.shape);
+        let selected: Vec<usize> = (0..self.data.len())
+            .filter(|&offset| mask.data[offset])
+            .collect();
+
+        Tensor::from_operation(
+            format!("MaskedSelect({})", format_name(self)),
+            &[selected.len()],
+            selected.iter().map(|&offset| self.data[offset]).collect(),
+            GradientOperation::Select(self.clone(), selected),
+        )
+    }
+
+    /// Consecutive pieces of `axis` with the given sizes, which must add up to its length.
+    fn split(&self, sizes: &[usize], axis: usize) -> Vec<Tensor<T>> {
+        assert!(
+            axis < self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        assert_eq!(
+            sizes.iter().sum::<usize>(),
+            self.shape[axis],
+            "Cannot split axis {} of {:?} into {:?}",
+            axis,
+            self.shape,
+            sizes
+        );
+        let mut start = 0;
+        sizes
+            .iter()
+            .map(|&len| {
+                start += len;
+                self.narrow(axis, start - len, len)
             })
-        });
+            .collect()
+    }
 
-        Tensor {
-            name: format!("({}^{})", format_name(self), exp),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                last: Some(Tensor::from_vector(data)),
-                operation: GradientOperation::Pow(self.clone(), exp),
-                value: Some(Tensor::fill(m, n, 0.0)),
+    /// Splits `axis` into `n` pieces of equal size, except for a smaller last piece if it
+    /// doesn't divide evenly. Returns fewer pieces if the axis is shorter than `n`.
+    fn chunk(&self, n: usize, axis: usize) -> Vec<Tensor<T>> {
+        assert!(n > 0, "Cannot split into 0 chunks");
+        assert!(
+            axis < self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        let dim = self.shape[axis];
+        let size = dim.div_ceil(n).max(1);
+        let sizes: Vec<usize> = (0..dim)
+            .step_by(size)
+            .map(|start| size.min(dim - start))
+            .collect();
+        self.split(&sizes, axis)
+    }
+
+    /// Picks elements along `axis` by `index`, which has the same rank as `self` and holds
+    /// positions: `y[i][j] = self[index[i][j]][j]` for axis 0. The result has the
+    /// shape of `index`.
+    fn gather(&self, axis: usize, index: &Tensor<i64>) -> Tensor<T> {
+        let selected = self.index_offsets(axis, index);
+
+        Tensor::from_operation(
+            format!("Gather_{}({})", axis, format_name(self)),
+            &index.shape,
+            selected.iter().map(|&offset| self.data[offset]).collect(),
+            GradientOperation::Select(self.clone(), selected),
+        )
+    }
+
+    /// Adds every element of `src` covered by `index` to `self` at the position `index`
+    /// gives along `axis`: `y[index[i][j]][j] += src[i][j]` for axis 0. Repeated positions
+    /// accumulate.
+    fn scatter_add(&self, axis: usize, index: &Tensor<i64>, src: &Tensor<T>) -> Tensor<T> {
+        assert!(
+            index
+                .shape
+                .iter()
+                .zip(src.shape.iter())
+                .all(|(i, s)| i <= s),
+            "Index of shape {:?} doesn't fit in source of shape {:?}",
+            index.shape,
+            src.shape
+        );
+        let pairs: Vec<(usize, usize)> = self
+            .index_offsets(axis, index)
+            .into_iter()
+            .enumerate()
+            .map(|(position, k)| (src.offset(&index.unravel(position)), k))
+            .collect();
+        let mut data = self.data.clone();
+        for &(j, k) in &pairs {
+            data[k] += src.data[j];
+        }
+
+        Tensor::from_operation(
+            format!(
+                "ScatterAdd_{}({}, {})",
+                axis,
+                format_name(self),
+                format_name(src)
+            ),
+            &self.shape,
+            data,
+            GradientOperation::ScatterAdd(self.clone(), src.clone(), pairs),
+        )
+    }
+
+    /// `value` wherever the broadcast `mask` holds, `self` elsewhere.
+    fn masked_fill(&self, mask: &Tensor<bool>, value: f64) -> Tensor<T> {
+        Tensor::where_(mask, &Tensor::scalar(T::from_f64(value)), self)
+    }
+
+    /// Matrix product over the last two axes, broadcasting any leading (batch) axes like `+`:
+    /// `[..., m, n] * [..., n, p] => [..., m, p]`. As in NumPy, a rank-1 left operand is
+    /// treated as a row vector and a rank-1 right operand as a column vector, and the added
+    /// axis is removed from the result.
+    fn matmul(&self, right: &Tensor<T>) -> Tensor<T> {
+        assert!(
+            self.rank() >= 1 && right.rank() >= 1,
+            "Matrix multiplication expects tensors of rank 1 or more, got {:?} * {:?}",
+            self.shape,
+            right.shape
+        );
+        match (self.rank(), right.rank()) {
+            (1, 1) => return self.unsqueeze(0).matmul(&right.unsqueeze(1)).reshape(&[]),
+            (1, _) => return self.unsqueeze(0).matmul(right).squeeze(right.rank() - 2),
+            (_, 1) => return self.matmul(&right.unsqueeze(1)).squeeze(self.rank() - 1),
+            _ => {}
+        }
+
+        let (m, n_1) = (self.shape[self.rank() - 2], self.shape[self.rank() - 1]);
+        let (n_2, p) = (right.shape[right.rank() - 2], right.shape[right.rank() - 1]);
+
+        // [m x n_1][n_2 x p] => [m x p]
+        if n_1 != n_2 {
+            panic!("Incompatible dimensions: [{m} x n_1: {n_1}][n_2: {n_2} x {p}], n_1 != n_2")
+        }
+        let batch = broadcast_shape(
+            &self.shape[..self.rank() - 2],
+            &right.shape[..right.rank() - 2],
+        );
+        let left = self.broadcast_to(&[batch.as_slice(), &[m, n_1]].concat());
+        let right_operand = right.broadcast_to(&[batch.as_slice(), &[n_2, p]].concat());
+
+        let mut data = vec![T::zero(); batch.iter().product::<usize>() * m * p];
+        // An empty [m x p] output has nothing to compute, and chunks can't be empty
+        if m * p > 0 {
+            for (b, out) in data.chunks_mut(m * p).enumerate() {
+                T::gemm(
+                    &left.data[b * m * n_1..(b + 1) * m * n_1],
+                    &right_operand.data[b * n_2 * p..(b + 1) * n_2 * p],
+                    out,
+                    (m, n_1, p),
+                );
             }
-            .wrap(),
         }
+
+        Tensor::from_operation(
+            binary_label(self, "*".to_string(), right),
+            &[batch.as_slice(), &[m, p]].concat(),
+            data.into(),
+            GradientOperation::Mul(self.clone(), right.clone()),
+        )
     }
 }
 
 // Unary operations
-impl Neg for Tensor {
-    type Output = Tensor;
+impl<T: Float> Neg for Tensor<T> {
+    type Output = Tensor<T>;
 
     fn neg(self) -> Self::Output {
         -&self
     }
 }
 
-impl<'a> Neg for &'a Tensor {
-    type Output = Tensor;
-    fn neg(self) -> Tensor {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
-
-        for i in 0..m {
-            for j in 0..n {
-                data[i][j] = -self[i][j];
-            }
-        }
+impl<T: Float> Neg for &Tensor<T> {
+    type Output = Tensor<T>;
+    fn neg(self) -> Tensor<T> {
+        let data = simd::neg(&self.data);
 
-        Tensor {
-            name: unary_label("-".to_string(), self),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                last: Some(Tensor::from_vector(data)),
-                operation: GradientOperation::Neg(self.clone()),
-                value: Some(Tensor::fill(m, n, 0.0)),
-            }
-            .wrap(),
-        }
+        Tensor::from_operation(
+            unary_label("-".to_string(), self),
+            &self.shape,
+            data.into(),
+            GradientOperation::Neg(self.clone()),
+        )
     }
 }
 
 // In-place non-gradient operations
-impl<'a> AddAssign<Tensor> for &'a mut Tensor {
+impl<T: Float> AddAssign<Tensor<T>> for &mut Tensor<T> {
     // NON-GRADIENT
-    fn add_assign(&mut self, right: Tensor) {
-        let (m, n) = self.size;
-        assert!((m, n) == right.size);
-        for i in 0..m {
-            for j in 0..n {
-                self[i][j] = self[i][j] + right[i][j];
-            }
-        }
+    fn add_assign(&mut self, right: Tensor<T>) {
+        **self += &right;
     }
 }
 
-impl<'a> SubAssign<Tensor> for &'a mut Tensor {
+impl<T: Float> SubAssign<Tensor<T>> for &mut Tensor<T> {
     // NON-GRADIENT
-    fn sub_assign(&mut self, right: Tensor) {
-        let (m, n) = self.size;
-        assert!((m, n) == right.size);
-        for i in 0..m {
-            for j in 0..n {
-                self[i][j] = self[i][j] - right[i][j];
-            }
-        }
+    fn sub_assign(&mut self, right: Tensor<T>) {
+        **self -= &right;
     }
 }
 
 // Binary operations
-impl<'a> Add<&'a Tensor> for &'a Tensor {
-    type Output = Tensor;
-    fn add(self, right: &'a Tensor) -> Tensor {
-        let (m, n) = self.size;
-        let (m_2, n_2) = right.size;
-        assert!((m, n) == right.size, "({}, {}) != ({}, {})", m, n, m_2, n_2);
-        let mut data = vec![vec![0.0; n]; m];
-        for i in 0..m {
-            for j in 0..n {
-                data[i][j] = self[i][j] + right[i][j];
-            }
-        }
+impl<'a, T: Float> Add<&'a Tensor<T>> for &'a Tensor<T> {
+    type Output = Tensor<T>;
+    fn add(self, right: &'a Tensor<T>) -> Tensor<T> {
+        let result = self.zip_elementwise(right, simd::add, |x, y| x + y);
 
-        Tensor {
-            name: binary_label(self, "+".to_string(), right),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                operation: GradientOperation::Add(self.clone(), right.clone()),
-                last: Some(Tensor::from_vector(data)),
-                value: Some(Tensor::fill(m, n, 0.0)),
-            }
-            .wrap(),
-        }
+        Tensor::from_operation(
+            binary_label(self, "+".to_string(), right),
+            &result.shape,
+            result.data,
+            GradientOperation::Add(self.clone(), right.clone()),
+        )
     }
 }
 
-impl Add<Tensor> for Tensor {
-    type Output = Tensor;
+impl<T: Float> Add<Tensor<T>> for Tensor<T> {
+    type Output = Tensor<T>;
 
-    fn add(self, rhs: Tensor) -> Self::Output {
+    fn add(self, rhs: Tensor<T>) -> Self::Output {
         &self + &rhs
     }
 }
 
-impl<'a> Sub<&'a Tensor> for &'a Tensor {
-    type Output = Tensor;
-    fn sub(self, right: &'a Tensor) -> Tensor {
-        let (m, n) = self.size;
-        let (m_2, n_2) = right.size;
-        if n != n_2 {
-            panic!("Incompatible dimensions: [{m}x{n}] - [{m_2}x{n_2}]");
-        }
-        assert!((m, n) == right.size);
-        let mut data = vec![vec![0.0; n]; m];
-        for i in 0..m {
-            for j in 0..n {
-                data[i][j] = self[i][j] - right[i][j];
-            }
-        }
+impl<'a, T: Float> Sub<&'a Tensor<T>> for &'a Tensor<T> {
+    type Output = Tensor<T>;
+    fn sub(self, right: &'a Tensor<T>) -> Tensor<T> {
+        let result = self.zip_elementwise(right, simd::sub, |x, y| x - y);
 
-        Tensor {
-            name: binary_label(self, "-".to_string(), right),
-            data: data.clone(),
-            size: (m, n),
-            gradient: Gradient {
-                operation: GradientOperation::Sub(self.clone(), right.clone()),
-                last: Some(Tensor::from_vector(data)),
-                value: Some(Tensor::fill(m, n, 0.0)),
-            }
-            .wrap(),
-        }
+        Tensor::from_operation(
+            binary_label(self, "-".to_string(), right),
+            &result.shape,
+            result.data,
+            GradientOperation::Sub(self.clone(), right.clone()),
+        )
     }
 }
 
-impl Sub<Tensor> for Tensor {
-    type Output = Tensor;
+impl<T: Float> Sub<Tensor<T>> for Tensor<T> {
+    type Output = Tensor<T>;
 
-    fn sub(self, right: Tensor) -> Self::Output {
+    fn sub(self, right: Tensor<T>) -> Self::Output {
         &self - &right
     }
 }
 
-impl<'a> Mul<&'a Tensor> for &'a Tensor {
-    type Output = Tensor;
-    fn mul(self, right: &'a Tensor) -> Tensor {
-        let (m, n_1) = self.size;
-        let (n_2, p) = right.size;
+impl<'a, T: Float> Mul<&'a Tensor<T>> for &'a Tensor<T> {
+    type Output = Tensor<T>;
+    fn mul(self, right: &'a Tensor<T>) -> Tensor<T> {
+        self.matmul(right)
+    }
+}
 
-        // [m x n_1][n_2 x p] => [m x p]
-        if n_1 != n_2 {
-            panic!("Incompatible dimensions: [{m} x n_1: {n_1}][n_2: {n_2} x {p}], n_1 != n_2")
-        }
-        let mut data = vec![vec![0.0; p]; m];
+impl<T: Float> Mul<Tensor<T>> for Tensor<T> {
+    type Output = Tensor<T>;
 
-        // TODO: loop over (i,j,k) tuples
-        for i in 0..m {
-            for j in 0..p {
-                for k in 0..n_1 {
-                    data[i][j] += self[i][k] * right[k][j];
-                }
-            }
-        }
-
-        Tensor {
-            name: binary_label(self, "*".to_string(), right),
-            data: data.clone(),
-            size: (m, p),
-            gradient: Gradient {
-                operation: GradientOperation::Mul(self.clone(), right.clone()),
-                last: Some(Tensor::from_vector(data)),
-                value: Some(Tensor::fill(m, p, 0.0)),
-            }
-            .wrap(),
-        }
+    fn mul(self, right: Tensor<T>) -> Self::Output {
+        &self * &right
     }
 }
 
-impl Mul<Tensor> for Tensor {
-    type Output = Tensor;
+impl<'a, T: Float> Div<&'a Tensor<T>> for &'a Tensor<T> {
+    type Output = Tensor<T>;
 
-    fn mul(self, right: Tensor) -> Self::Output {
-        &self * &right
+    fn div(self, right: &'a Tensor<T>) -> Tensor<T> {
+        Differentiable::div(self, right)
     }
 }
 
-impl Mul<f64> for Tensor {
-    type Output = Tensor;
+impl<T: Float> Div<Tensor<T>> for Tensor<T> {
+    type Output = Tensor<T>;
 
-    fn mul(self, right: f64) -> Self::Output {
-        &self * right
+    fn div(self, right: Tensor<T>) -> Self::Output {
+        &self / &right
     }
 }
 
-impl Mul<f64> for &Tensor {
-    type Output = Tensor;
+// Scalar operations, recorded in the graph by broadcasting a rank-0 constant
+macro_rules! scalar_operation {
+    ($type:ty, $trait:ident, $method:ident, $operation:path) => {
+        impl $trait<$type> for &Tensor<$type> {
+            type Output = Tensor<$type>;
 
-    fn mul(self, right: f64) -> Self::Output {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
-        for i in 0..m {
-            for j in 0..n {
-                data[i][j] += self[i][j] * right;
+            fn $method(self, right: $type) -> Tensor<$type> {
+                $operation(self, &Tensor::scalar(right))
             }
         }
 
-        Tensor {
-            data: data.clone(),
-            size: (m, n),
-            ..Tensor::default()
+        impl $trait<$type> for Tensor<$type> {
+            type Output = Tensor<$type>;
+
+            fn $method(self, right: $type) -> Tensor<$type> {
+                $operation(&self, &Tensor::scalar(right))
+            }
         }
-    }
+    };
 }
 
-impl Mul<Tensor> for f64 {
-    type Output = Tensor;
+macro_rules! reversed_scalar_operation {
+    ($type:ty, $trait:ident, $method:ident, $operation:path) => {
+        impl $trait<&Tensor<$type>> for $type {
+            type Output = Tensor<$type>;
 
-    fn mul(self, rhs: Tensor) -> Self::Output {
-        rhs * self
-    }
+            fn $method(self, right: &Tensor<$type>) -> Tensor<$type> {
+                $operation(&Tensor::scalar(self), right)
+            }
+        }
+
+        impl $trait<Tensor<$type>> for $type {
+            type Output = Tensor<$type>;
+
+            fn $method(self, right: Tensor<$type>) -> Tensor<$type> {
+                $operation(&Tensor::scalar(self), &right)
+            }
+        }
+    };
 }
 
-impl PartialEq for Tensor {
+scalar_operation!(f64, Add, add, Add::add);
+scalar_operation!(f64, Sub, sub, Sub::sub);
+scalar_operation!(f64, Mul, mul, Differentiable::mul_elem);
+scalar_operation!(f64, Div, div, Differentiable::div);
+scalar_operation!(f32, Add, add, Add::add);
+scalar_operation!(f32, Sub, sub, Sub::sub);
+scalar_operation!(f32, Mul, mul, Differentiable::mul_elem);
+scalar_operation!(f32, Div, div, Differentiable::div);
+
+// A float literal on the left, as in `2.0 * &x`, only infers if a single float type implements
+// the operator, so the scalar-first forms are limited to the default element type.
+reversed_scalar_operation!(f64, Add, add, Add::add);
+reversed_scalar_operation!(f64, Sub, sub, Sub::sub);
+reversed_scalar_operation!(f64, Mul, mul, Differentiable::mul_elem);
+reversed_scalar_operation!(f64, Div, div, Differentiable::div);
+
+impl<T: Element> PartialEq for Tensor<T> {
     fn eq(&self, other: &Self) -> bool {
-        let (m, n) = self.size;
-        let (m_2, n_2) = other.size;
-        if m != m_2 || n != n_2 {
+        if self.shape != other.shape {
             return false;
         }
-        for i in 0..m {
-            for j in 0..n {
-                if self[i][j] != other[i][j] {
-                    return false;
-                }
-            }
-        }
-        true
+        self.data.iter().zip(other.data.iter()).all(|(x, y)| x == y)
     }
 }
@@ -0,0 +1,112 @@
+// Scalar activation functions and their derivatives, shared by the forward and backward passes.
+use std::f64::consts::{FRAC_2_SQRT_PI, SQRT_2};
+
+use crate::dtype::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeluApproximation {
+    /// x * Φ(x), with Φ the standard normal CDF
+    None,
+    /// 0.5x(1 + tanh(sqrt(2/π)(x + 0.044715x^3)))
+    Tanh,
+}
+
+const GELU_TANH_COEFFICIENT: f64 = 0.044715;
+
+fn constant<T: Float>(value: f64) -> T {
+    T::from_f64(value)
+}
+
+pub fn sigmoid<T: Float>(x: T) -> T {
+    // Only ever exponentiate a non-positive number so neither branch overflows
+    match x {
+        x if x >= T::zero() => T::one() / (T::one() + (-x).exp()),
+        x => x.exp() / (T::one() + x.exp()),
+    }
+}
+
+pub fn sigmoid_derivative<T: Float>(x: T) -> T {
+    let s = sigmoid(x);
+    s * (T::one() - s)
+}
+
+pub fn tanh_derivative<T: Float>(x: T) -> T {
+    T::one() - x.tanh().powi(2)
+}
+
+pub fn gelu<T: Float>(x: T, approximation: GeluApproximation) -> T {
+    let half: T = constant(0.5);
+    match approximation {
+        GeluApproximation::None => x * normal_cdf(x),
+        GeluApproximation::Tanh => half * x * (T::one() + gelu_tanh_inner(x).tanh()),
+    }
+}
+
+pub fn gelu_derivative<T: Float>(x: T, approximation: GeluApproximation) -> T {
+    let half: T = constant(0.5);
+    match approximation {
+        GeluApproximation::None => normal_cdf(x) + x * normal_pdf(x),
+        GeluApproximation::Tanh => {
+            let t = gelu_tanh_inner(x).tanh();
+            let inner_derivative = constant::<T>(FRAC_2_SQRT_PI / SQRT_2)
+                * (T::one() + constant::<T>(3.0 * GELU_TANH_COEFFICIENT) * x * x);
+            half * (T::one() + t) + half * x * (T::one() - t * t) * inner_derivative
+        }
+    }
+}
+
+pub fn leaky_relu<T: Float>(x: T, slope: T) -> T {
+    match x {
+        x if x >= T::zero() => x,
+        x => slope * x,
+    }
+}
+
+pub fn leaky_relu_derivative<T: Float>(x: T, slope: T) -> T {
+    match x {
+        x if x >= T::zero() => T::one(),
+        _ => slope,
+    }
+}
+
+pub fn elu<T: Float>(x: T, alpha: T) -> T {
+    match x {
+        x if x > T::zero() => x,
+        x => alpha * x.exp_m1(),
+    }
+}
+
+pub fn elu_derivative<T: Float>(x: T, alpha: T) -> T {
+    match x {
+        x if x > T::zero() => T::one(),
+        x => alpha * x.exp(),
+    }
+}
+
+pub fn silu<T: Float>(x: T) -> T {
+    x * sigmoid(x)
+}
+
+pub fn silu_derivative<T: Float>(x: T) -> T {
+    let s = sigmoid(x);
+    s * (T::one() + x * (T::one() - s))
+}
+
+fn gelu_tanh_inner<T: Float>(x: T) -> T {
+    // sqrt(2/π) = (2/sqrt(π)) / sqrt(2)
+    constant::<T>(FRAC_2_SQRT_PI / SQRT_2) * (x + constant::<T>(GELU_TANH_COEFFICIENT) * x.powi(3))
+}
+
+fn normal_pdf<T: Float>(x: T) -> T {
+    // e^(-x^2/2) / sqrt(2π)
+    (constant::<T>(-0.5) * x * x).exp() * constant(FRAC_2_SQRT_PI / (2.0 * SQRT_2))
+}
+
+fn normal_cdf<T: Float>(x: T) -> T {
+    constant::<T>(0.5) * erfc(-x / constant(SQRT_2))
+}
+
+/// Complementary error function, computed in f64 to full precision.
+fn erfc<T: Float>(x: T) -> T {
+    T::from_f64(libm::erfc(x.into_f64()))
+}
@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use crate::{operations::Differentiable, tensor::Tensor};
+
+/// Step size and tolerances for `gradcheck`. An element passes when
+/// `|analytic - numeric| <= absolute + relative * |numeric|`.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub epsilon: f64,
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            epsilon: 1e-6,
+            absolute: 1e-5,
+            relative: 1e-3,
+        }
+    }
+}
+
+impl Tolerance {
+    fn allowed(&self, numeric: f64) -> f64 {
+        self.absolute + self.relative * numeric.abs()
+    }
+}
+
+/// The element of one input whose analytic gradient strays furthest from the numerical one.
+#[derive(Debug, Clone)]
+pub struct Discrepancy {
+    pub input: usize,
+    pub index: Vec<usize>,
+    pub analytic: f64,
+    pub numeric: f64,
+    pub passed: bool,
+}
+
+impl Discrepancy {
+    pub fn error(&self) -> f64 {
+        (self.analytic - self.numeric).abs()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GradCheckReport {
+    pub tolerance: Tolerance,
+    /// Worst element per input, `None` for inputs without any elements.
+    pub worst: Vec<Option<Discrepancy>>,
+}
+
+impl GradCheckReport {
+    pub fn passed(&self) -> bool {
+        self.worst.iter().flatten().all(|worst| worst.passed)
+    }
+}
+
+impl Display for GradCheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for worst in self.worst.iter().flatten() {
+            writeln!(
+                f,
+                "input {} at {:?}: analytic = {}, numeric = {}, error = {} ({})",
+                worst.input,
+                worst.index,
+                worst.analytic,
+                worst.numeric,
+                worst.error(),
+                if worst.passed { "ok" } else { "FAILED" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the gradients `backward` computes for `f` against central finite differences.
+///
+/// `f` maps the inputs to a single-element tensor. The inputs are copied, so their own
+/// gradients are left untouched. Returns `Err` with the same report when any input has an
+/// element outside `tolerance`, so tests can simply `unwrap()` the result.
+pub fn gradcheck(
+    f: impl Fn(&[Tensor]) -> Tensor,
+    inputs: &[Tensor],
+    tolerance: Tolerance,
+) -> Result<GradCheckReport, GradCheckReport> {
+    let copy = |inputs: &[Tensor]| -> Vec<Tensor> {
+        inputs
+            .iter()
+            .map(|x| Tensor::from_storage(&x.shape, x.data.clone()))
+            .collect()
+    };
+    let evaluate = |inputs: &[Tensor]| -> f64 {
+        let y = f(inputs);
+        assert_eq!(
+            1,
+            y.num_elements(),
+            "gradcheck expects a scalar output, got shape {:?}",
+            y.shape
+        );
+        y.item()
+    };
+
+    let variables: Vec<Tensor> = copy(inputs).into_iter().map(|x| x.with_grad()).collect();
+    let y = f(&variables);
+    y.set_grad(Tensor::full(&y.shape, 1.0));
+    y.backward();
+
+    let worst = variables
+        .iter()
+        .enumerate()
+        .map(|(k, variable)| {
+            let analytic = variable.grad();
+            (0..variable.data.len())
+                .map(|i| {
+                    let nudged = |delta: f64| {
+                        let mut nudged = copy(inputs);
+                        nudged[k].data[i] += delta;
+                        evaluate(&nudged)
+                    };
+                    let numeric = (nudged(tolerance.epsilon) - nudged(-tolerance.epsilon))
+                        / (2.0 * tolerance.epsilon);
+                    let scaled_error =
+                        (analytic.data[i] - numeric).abs() / tolerance.allowed(numeric);
+                    let discrepancy = Discrepancy {
+                        input: k,
+                        index: variable.unravel(i),
+                        analytic: analytic.data[i],
+                        numeric,
+                        passed: scaled_error <= 1.0,
+                    };
+                    (scaled_error, discrepancy)
+                })
+                .max_by(|(a, _), (b, _)| a.total_cmp(b))
+                .map(|(_, discrepancy)| discrepancy)
+        })
+        .collect();
+
+    let report = GradCheckReport { tolerance, worst };
+    match report.passed() {
+        true => Ok(report),
+        false => Err(report),
+    }
+}
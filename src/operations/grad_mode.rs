@@ -0,0 +1,43 @@
+use std::cell::Cell;
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Whether operations on this thread currently record themselves in the autograd graph.
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+/// Disables graph construction on this thread until dropped, then restores the previous mode.
+///
+/// ```
+/// use llm_rs::{operations::{Differentiable, NoGradGuard}, tensor::Tensor};
+///
+/// let x = Tensor::singleton(2.0).with_grad();
+/// let _guard = NoGradGuard::new();
+/// assert!(!(-&x).has_grad());
+/// ```
+pub struct NoGradGuard {
+    previous: bool,
+}
+
+impl NoGradGuard {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> NoGradGuard {
+        let previous = GRAD_ENABLED.with(|enabled| enabled.replace(false));
+        NoGradGuard { previous }
+    }
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+/// Runs `f` without building a graph, e.g. for evaluation or parameter updates.
+pub fn no_grad<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = NoGradGuard::new();
+    f()
+}
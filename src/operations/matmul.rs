@@ -0,0 +1,190 @@
+// Matrix multiplication kernels over contiguous row-major buffers.
+use std::thread;
+
+use crate::dtype::Float;
+
+/// Side of the square blocks the tiled kernel works through. A block of `b` and the matching
+/// stretch of an `out` row stay in cache while every row of `a` passes over them.
+const TILE: usize = 64;
+
+/// Products with fewer multiply-adds than this run on the calling thread, as spawning threads
+/// would cost more than it saves.
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
+/// `out += a.b` for a row-major `[m x n]` matrix `a` and `[n x p]` matrix `b`, one dot product
+/// at a time. Kept as the reference the tiled kernel is tested and benchmarked against.
+pub fn naive<T: Float>(a: &[T], b: &[T], out: &mut [T], (m, n, p): (usize, usize, usize)) {
+    for i in 0..m {
+        for j in 0..p {
+            for k in 0..n {
+                out[i * p + j] += a[i * n + k] * b[k * p + j];
+            }
+        }
+    }
+}
+
+/// `out += a.b` like `naive`, but tiled for cache reuse and, for large products, with the
+/// rows of `out` split across threads.
+pub fn tiled<T: Float>(a: &[T], b: &[T], out: &mut [T], (m, n, p): (usize, usize, usize)) {
+    check_buffers(a.len(), b.len(), out.len(), (m, n, p));
+    let threads = match m * n * p {
+        work if work < PARALLEL_THRESHOLD => 1,
+        _ => thread::available_parallelism().map_or(1, |count| count.get()),
+    };
+    let rows = m.div_ceil(threads).max(1);
+    if rows >= m {
+        return tiles(a, b, out, (m, n, p));
+    }
+    for_each_block(out, rows * p, |block, out| {
+        let first = block * rows;
+        let count = out.len() / p;
+        tiles(&a[first * n..(first + count) * n], b, out, (count, n, p));
+    });
+}
+
+fn check_buffers(a: usize, b: usize, out: usize, (m, n, p): (usize, usize, usize)) {
+    assert!(
+        a == m * n && b == n * p && out == m * p,
+        "Buffers don't match [{m} x {n}].[{n} x {p}]"
+    );
+}
+
+/// Accumulates `a.b` into `out` block by block: each row of `a` is multiplied into a
+/// `TILE x TILE` block of `b`, so the innermost loop walks contiguous rows of `b` and `out`.
+fn tiles<T: Float>(a: &[T], b: &[T], out: &mut [T], (m, n, p): (usize, usize, usize)) {
+    for k_start in (0..n).step_by(TILE) {
+        let k_end = (k_start + TILE).min(n);
+        for j_start in (0..p).step_by(TILE) {
+            let j_end = (j_start + TILE).min(p);
+            for i in 0..m {
+                let out_row = &mut out[i * p + j_start..i * p + j_end];
+                for k in k_start..k_end {
+                    let a_ik = a[i * n + k];
+                    let b_row = &b[k * p + j_start..k * p + j_end];
+                    for (out, &b_kj) in out_row.iter_mut().zip(b_row) {
+                        *out += a_ik * b_kj;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs `fun(index, block)` on every `size`-element block of `out`, each on its own thread.
+#[cfg(not(feature = "rayon"))]
+fn for_each_block<T: Float>(out: &mut [T], size: usize, fun: impl Fn(usize, &mut [T]) + Sync) {
+    thread::scope(|scope| {
+        for (index, block) in out.chunks_mut(size).enumerate() {
+            let fun = &fun;
+            scope.spawn(move || fun(index, block));
+        }
+    });
+}
+
+/// Runs `fun(index, block)` on every `size`-element block of `out` on rayon's thread pool.
+#[cfg(feature = "rayon")]
+fn for_each_block<T: Float>(out: &mut [T], size: usize, fun: impl Fn(usize, &mut [T]) + Sync) {
+    use rayon::prelude::*;
+
+    out.par_chunks_mut(size)
+        .enumerate()
+        .for_each(|(index, block)| fun(index, block));
+}
+
+/// Element types `Tensor::matmul` can multiply. With the `blas` feature this goes through the
+/// system CBLAS, otherwise through `tiled`.
+pub trait Gemm: Sized {
+    /// `out += a.b` for a row-major `[m x n]` matrix `a` and `[n x p]` matrix `b`.
+    fn gemm(a: &[Self], b: &[Self], out: &mut [Self], dims: (usize, usize, usize));
+}
+
+macro_rules! gemm {
+    ($type:ty, $cblas:ident) => {
+        impl Gemm for $type {
+            #[cfg(not(feature = "blas"))]
+            fn gemm(a: &[Self], b: &[Self], out: &mut [Self], dims: (usize, usize, usize)) {
+                tiled(a, b, out, dims)
+            }
+
+            #[cfg(feature = "blas")]
+            fn gemm(a: &[Self], b: &[Self], out: &mut [Self], (m, n, p): (usize, usize, usize)) {
+                check_buffers(a.len(), b.len(), out.len(), (m, n, p));
+                if m == 0 || n == 0 || p == 0 {
+                    return;
+                }
+                let dim = |dim: usize| {
+                    std::os::raw::c_int::try_from(dim).expect("Matrix too large for BLAS")
+                };
+                // Safety: the buffers were checked to hold exactly [m x n], [n x p] and [m x p]
+                // row-major matrices, and `out` is borrowed mutably so it can't alias `a` or `b`.
+                unsafe {
+                    cblas::$cblas(
+                        cblas::ROW_MAJOR,
+                        cblas::NO_TRANS,
+                        cblas::NO_TRANS,
+                        dim(m),
+                        dim(p),
+                        dim(n),
+                        1.0,
+                        a.as_ptr(),
+                        dim(n),
+                        b.as_ptr(),
+                        dim(p),
+                        1.0,
+                        out.as_mut_ptr(),
+                        dim(p),
+                    )
+                }
+            }
+        }
+    };
+}
+
+gemm!(f64, cblas_dgemm);
+gemm!(f32, cblas_sgemm);
+
+/// The parts of the CBLAS interface we use. Which library provides it is picked at link time,
+/// see `build.rs`.
+#[cfg(feature = "blas")]
+mod cblas {
+    use std::os::raw::c_int;
+
+    pub const ROW_MAJOR: c_int = 101;
+    pub const NO_TRANS: c_int = 111;
+
+    extern "C" {
+        pub fn cblas_dgemm(
+            layout: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f64,
+            a: *const f64,
+            lda: c_int,
+            b: *const f64,
+            ldb: c_int,
+            beta: f64,
+            c: *mut f64,
+            ldc: c_int,
+        );
+
+        pub fn cblas_sgemm(
+            layout: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f32,
+            a: *const f32,
+            lda: c_int,
+            b: *const f32,
+            ldb: c_int,
+            beta: f32,
+            c: *mut f32,
+            ldc: c_int,
+        );
+    }
+}
@@ -0,0 +1,358 @@
+// Elementwise kernels that process several elements per instruction. On x86_64 they use the
+// widest of AVX2 and SSE2 the CPU supports, detected at runtime; elsewhere they run a scalar loop.
+
+/// `a + b` elementwise over equal-length buffers.
+pub fn add<T: Simd>(a: &[T], b: &[T]) -> Vec<T> {
+    T::add(a, b)
+}
+
+/// `a - b` elementwise over equal-length buffers.
+pub fn sub<T: Simd>(a: &[T], b: &[T]) -> Vec<T> {
+    T::sub(a, b)
+}
+
+pub fn neg<T: Simd>(a: &[T]) -> Vec<T> {
+    T::neg(a)
+}
+
+/// `max(x, 0)` for every `x`; -0.0 and NaNs become 0.
+pub fn relu<T: Simd>(a: &[T]) -> Vec<T> {
+    T::relu(a)
+}
+
+/// `x^exp` for every `x`, by repeated squaring.
+pub fn powi<T: Simd>(a: &[T], exp: i32) -> Vec<T> {
+    T::powi(a, exp)
+}
+
+/// `a += b` elementwise over equal-length buffers.
+pub fn add_assign<T: Simd>(a: &mut [T], b: &[T]) {
+    T::add_assign(a, b)
+}
+
+/// `a -= b` elementwise over equal-length buffers.
+pub fn sub_assign<T: Simd>(a: &mut [T], b: &[T]) {
+    T::sub_assign(a, b)
+}
+
+/// Element types with vectorized kernels. Binary kernels panic unless both buffers have the same
+/// length, since the vector loops read `b` for every element of `a`.
+pub trait Simd: Sized {
+    fn add(a: &[Self], b: &[Self]) -> Vec<Self>;
+    fn sub(a: &[Self], b: &[Self]) -> Vec<Self>;
+    fn neg(a: &[Self]) -> Vec<Self>;
+    fn relu(a: &[Self]) -> Vec<Self>;
+    fn powi(a: &[Self], exp: i32) -> Vec<Self>;
+    fn add_assign(a: &mut [Self], b: &[Self]);
+    fn sub_assign(a: &mut [Self], b: &[Self]);
+}
+
+/// Runs `$kernel` from the `$avx` or `$sse` module if the CPU supports it, or the scalar one.
+macro_rules! dispatch {
+    ($avx:ident, $sse:ident, $kernel:ident($($arg:expr),*)) => {{
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // Safety: the CPU supports the kernel's target feature
+                unsafe { $avx::$kernel($($arg),*) }
+            } else if is_x86_feature_detected!("sse2") {
+                // Safety: as above
+                unsafe { $sse::$kernel($($arg),*) }
+            } else {
+                scalar::$kernel($($arg),*)
+            }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        scalar::$kernel($($arg),*);
+    }};
+}
+
+macro_rules! simd {
+    ($type:ty, $avx:ident, $sse:ident) => {
+        impl Simd for $type {
+            fn add(a: &[Self], b: &[Self]) -> Vec<Self> {
+                assert_eq!(a.len(), b.len(), "Buffers must have the same length");
+                let mut out = vec![0.0; a.len()];
+                dispatch!($avx, $sse, add(a, b, &mut out));
+                out
+            }
+
+            fn sub(a: &[Self], b: &[Self]) -> Vec<Self> {
+                assert_eq!(a.len(), b.len(), "Buffers must have the same length");
+                let mut out = vec![0.0; a.len()];
+                dispatch!($avx, $sse, sub(a, b, &mut out));
+                out
+            }
+
+            fn neg(a: &[Self]) -> Vec<Self> {
+                let mut out = vec![0.0; a.len()];
+                dispatch!($avx, $sse, neg(a, &mut out));
+                out
+            }
+
+            fn relu(a: &[Self]) -> Vec<Self> {
+                let mut out = vec![0.0; a.len()];
+                dispatch!($avx, $sse, relu(a, &mut out));
+                out
+            }
+
+            fn powi(a: &[Self], exp: i32) -> Vec<Self> {
+                let mut out = vec![0.0; a.len()];
+                dispatch!($avx, $sse, powi(a, exp, &mut out));
+                out
+            }
+
+            fn add_assign(a: &mut [Self], b: &[Self]) {
+                assert_eq!(a.len(), b.len(), "Buffers must have the same length");
+                dispatch!($avx, $sse, add_assign(a, b))
+            }
+
+            fn sub_assign(a: &mut [Self], b: &[Self]) {
+                assert_eq!(a.len(), b.len(), "Buffers must have the same length");
+                dispatch!($avx, $sse, sub_assign(a, b))
+            }
+        }
+    };
+}
+
+simd!(f64, avx2_f64, sse2_f64);
+simd!(f32, avx2_f32, sse2_f32);
+
+/// One implementation of every kernel for a register type. Each loop handles as many whole
+/// registers as fit and leaves the remainder to the scalar kernel.
+#[cfg(target_arch = "x86_64")]
+macro_rules! kernels {
+    (
+        $module:ident, $feature:literal, $type:ty, $width:literal,
+        $load:ident, $store:ident, $set1:ident,
+        $add:ident, $sub:ident, $mul:ident, $div:ident, $max:ident, $xor:ident
+    ) => {
+        mod $module {
+            use std::arch::x86_64::*;
+
+            use super::scalar;
+
+            const WIDTH: usize = $width;
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn add(a: &[$type], b: &[$type], out: &mut [$type]) {
+                let split = out.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    let (x, y) = ($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i)));
+                    $store(out.as_mut_ptr().add(i), $add(x, y));
+                }
+                scalar::add(&a[split..], &b[split..], &mut out[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn sub(a: &[$type], b: &[$type], out: &mut [$type]) {
+                let split = out.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    let (x, y) = ($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i)));
+                    $store(out.as_mut_ptr().add(i), $sub(x, y));
+                }
+                scalar::sub(&a[split..], &b[split..], &mut out[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn neg(a: &[$type], out: &mut [$type]) {
+                // Flipping the sign bit, so -0.0 and NaNs come out as `-x` would give them
+                let sign = $set1(-0.0);
+                let split = out.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    $store(
+                        out.as_mut_ptr().add(i),
+                        $xor($load(a.as_ptr().add(i)), sign),
+                    );
+                }
+                scalar::neg(&a[split..], &mut out[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn relu(a: &[$type], out: &mut [$type]) {
+                // max returns its second operand when either is NaN
+                let zero = $set1(0.0);
+                let split = out.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    $store(
+                        out.as_mut_ptr().add(i),
+                        $max($load(a.as_ptr().add(i)), zero),
+                    );
+                }
+                scalar::relu(&a[split..], &mut out[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn powi(a: &[$type], exp: i32, out: &mut [$type]) {
+                let one = $set1(1.0);
+                let split = out.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    let (mut base, mut result) = ($load(a.as_ptr().add(i)), one);
+                    let mut remaining = exp.unsigned_abs();
+                    while remaining > 0 {
+                        if remaining & 1 == 1 {
+                            result = $mul(result, base);
+                        }
+                        base = $mul(base, base);
+                        remaining >>= 1;
+                    }
+                    if exp < 0 {
+                        result = $div(one, result);
+                    }
+                    $store(out.as_mut_ptr().add(i), result);
+                }
+                scalar::powi(&a[split..], exp, &mut out[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn add_assign(a: &mut [$type], b: &[$type]) {
+                let split = a.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    let (x, y) = ($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i)));
+                    $store(a.as_mut_ptr().add(i), $add(x, y));
+                }
+                scalar::add_assign(&mut a[split..], &b[split..]);
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn sub_assign(a: &mut [$type], b: &[$type]) {
+                let split = a.len() / WIDTH * WIDTH;
+                for i in (0..split).step_by(WIDTH) {
+                    let (x, y) = ($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i)));
+                    $store(a.as_mut_ptr().add(i), $sub(x, y));
+                }
+                scalar::sub_assign(&mut a[split..], &b[split..]);
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+kernels!(
+    avx2_f64,
+    "avx2",
+    f64,
+    4,
+    _mm256_loadu_pd,
+    _mm256_storeu_pd,
+    _mm256_set1_pd,
+    _mm256_add_pd,
+    _mm256_sub_pd,
+    _mm256_mul_pd,
+    _mm256_div_pd,
+    _mm256_max_pd,
+    _mm256_xor_pd
+);
+#[cfg(target_arch = "x86_64")]
+kernels!(
+    sse2_f64,
+    "sse2",
+    f64,
+    2,
+    _mm_loadu_pd,
+    _mm_storeu_pd,
+    _mm_set1_pd,
+    _mm_add_pd,
+    _mm_sub_pd,
+    _mm_mul_pd,
+    _mm_div_pd,
+    _mm_max_pd,
+    _mm_xor_pd
+);
+#[cfg(target_arch = "x86_64")]
+kernels!(
+    avx2_f32,
+    "avx2",
+    f32,
+    8,
+    _mm256_loadu_ps,
+    _mm256_storeu_ps,
+    _mm256_set1_ps,
+    _mm256_add_ps,
+    _mm256_sub_ps,
+    _mm256_mul_ps,
+    _mm256_div_ps,
+    _mm256_max_ps,
+    _mm256_xor_ps
+);
+#[cfg(target_arch = "x86_64")]
+kernels!(
+    sse2_f32,
+    "sse2",
+    f32,
+    4,
+    _mm_loadu_ps,
+    _mm_storeu_ps,
+    _mm_set1_ps,
+    _mm_add_ps,
+    _mm_sub_ps,
+    _mm_mul_ps,
+    _mm_div_ps,
+    _mm_max_ps,
+    _mm_xor_ps
+);
+
+/// Fallback kernels, one element at a time. Also used for the tails of the vectorized ones.
+mod scalar {
+    use num_traits::Float;
+
+    pub fn add<T: Float>(a: &[T], b: &[T], out: &mut [T]) {
+        for ((out, &x), &y) in out.iter_mut().zip(a).zip(b) {
+            *out = x + y;
+        }
+    }
+
+    pub fn sub<T: Float>(a: &[T], b: &[T], out: &mut [T]) {
+        for ((out, &x), &y) in out.iter_mut().zip(a).zip(b) {
+            *out = x - y;
+        }
+    }
+
+    pub fn neg<T: Float>(a: &[T], out: &mut [T]) {
+        for (out, &x) in out.iter_mut().zip(a) {
+            *out = -x;
+        }
+    }
+
+    /// Matches `max(x, 0)` on registers, which gives 0 for -0.0 and NaN.
+    pub fn relu<T: Float>(a: &[T], out: &mut [T]) {
+        for (out, &x) in out.iter_mut().zip(a) {
+            *out = match x {
+                x if x > T::zero() => x,
+                _ => T::zero(),
+            };
+        }
+    }
+
+    /// The same repeated squaring as the register kernels, so results don't depend on
+    /// whether an element lands in a register or the tail.
+    pub fn powi<T: Float>(a: &[T], exp: i32, out: &mut [T]) {
+        for (out, &x) in out.iter_mut().zip(a) {
+            let (mut base, mut result) = (x, T::one());
+            let mut remaining = exp.unsigned_abs();
+            while remaining > 0 {
+                if remaining & 1 == 1 {
+                    result = result * base;
+                }
+                base = base * base;
+                remaining >>= 1;
+            }
+            *out = match exp < 0 {
+                true => T::one() / result,
+                false => result,
+            };
+        }
+    }
+
+    pub fn add_assign<T: Float>(a: &mut [T], b: &[T]) {
+        for (x, &y) in a.iter_mut().zip(b) {
+            *x = *x + y;
+        }
+    }
+
+    pub fn sub_assign<T: Float>(a: &mut [T], b: &[T]) {
+        for (x, &y) in a.iter_mut().zip(b) {
+            *x = *x - y;
+        }
+    }
+}
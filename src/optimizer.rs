@@ -1,8 +1,13 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{operations::Differentiable, tensor::Tensor};
+use crate::{
+    operations::{no_grad, Differentiable},
+    tensor::Tensor,
+};
 
 pub trait Optimizer {
+    /// Updates each parameter in place from its accumulated grad. The update runs under
+    /// `no_grad`, so it is never recorded in the graph.
     fn step(&self);
 }
 
@@ -24,10 +29,12 @@ impl StochasticGradientDescent {
 
 impl Optimizer for StochasticGradientDescent {
     fn step(&self) {
-        self.parameters.clone().into_iter().for_each(|parameter| {
-            let mut parameter = parameter.borrow_mut();
-            let weight_update = self.learning_rate * parameter.grad();
-            *parameter -= &weight_update;
+        no_grad(|| {
+            self.parameters.iter().for_each(|parameter| {
+                let mut parameter = parameter.borrow_mut();
+                let weight_update = self.learning_rate * parameter.grad();
+                *parameter -= &weight_update;
+            })
         });
     }
 }
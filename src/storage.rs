@@ -0,0 +1,71 @@
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::dtype::Element;
+
+/// Reference-counted element buffer behind a `Tensor`. Clones share the allocation, so graph
+/// nodes, `last` values and reshaped views are cheap handles. Writing through `DerefMut`
+/// copies the buffer first if anything else still shares it.
+#[derive(Clone, Default, PartialEq)]
+pub struct Storage<T: Element>(Rc<Vec<T>>);
+
+impl<T: Element> Storage<T> {
+    /// Whether `self` and `other` share one allocation.
+    pub fn shares(&self, other: &Storage<T>) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Element> From<Vec<T>> for Storage<T> {
+    fn from(data: Vec<T>) -> Self {
+        Storage(Rc::new(data))
+    }
+}
+
+impl<T: Element> FromIterator<T> for Storage<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Storage::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T: Element> Deref for Storage<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T: Element> DerefMut for Storage<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        Rc::make_mut(&mut self.0).as_mut_slice()
+    }
+}
+
+impl<'a, T: Element> IntoIterator for &'a Storage<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: Element> PartialEq<Vec<T>> for Storage<T> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl<T: Element> PartialEq<Storage<T>> for Vec<T> {
+    fn eq(&self, other: &Storage<T>) -> bool {
+        *self == *other.0
+    }
+}
+
+impl<T: Element> Debug for Storage<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
@@ -1,108 +1,339 @@
-use core::{f64, panic};
+use core::panic;
 use std::cell::RefCell;
 use std::fmt::{Debug, Display};
 use std::ops::{AddAssign, Index, IndexMut, SubAssign};
 use std::rc::Rc;
 
-use crate::operations::Gradient;
+use crate::dtype::{Element, Float};
+use crate::operations::{simd, Gradient};
+use crate::storage::Storage;
 
-pub struct Tensor {
+/// An n-dimensional array of `T`, which defaults to `f64`. Only tensors of floats (`f32`
+/// and `f64`) are differentiable; `i64` and `bool` tensors hold indices, labels and masks.
+/// Clones share `data`, which is copied on the first write while shared.
+pub struct Tensor<T: Element = f64> {
     pub name: String,
-    pub data: Vec<Vec<f64>>,
-    pub size: (usize, usize),
-    pub gradient: Rc<RefCell<Gradient>>,
+    pub data: Storage<T>,
+    pub shape: Vec<usize>,
+    pub strides: Vec<usize>,
+    pub gradient: Rc<RefCell<Gradient<T>>>,
 }
 
 // Housekeeping
-impl Default for Tensor {
-    fn default() -> Tensor {
+impl<T: Element> Default for Tensor<T> {
+    fn default() -> Tensor<T> {
         Tensor {
             name: String::new(),
-            data: vec![vec![0.0]],
-            size: (1, 1),
+            data: Storage::from(vec![T::default()]),
+            shape: vec![1, 1],
+            strides: vec![1, 1],
             gradient: Gradient::default().wrap(),
         }
     }
 }
 
+/// Row-major strides for a contiguous buffer of the given shape.
+pub fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
+/// Multi-dimensional index of the `position`th element of `shape` in row-major order.
+pub fn unravel(shape: &[usize], mut position: usize) -> Vec<usize> {
+    let mut index = vec![0; shape.len()];
+    for axis in (0..shape.len()).rev() {
+        index[axis] = position % shape[axis];
+        position /= shape[axis];
+    }
+    index
+}
+
+/// Position of the first element that no later element `beats`. NaNs win, as in NumPy.
+fn arg_best<T: Element>(lane: &[T], beats: impl Fn(T, T) -> bool) -> usize {
+    let is_nan = |x: T| x.partial_cmp(&x).is_none();
+    let mut best = 0;
+    for (i, &x) in lane.iter().enumerate() {
+        if is_nan(lane[best]) {
+            break;
+        }
+        if is_nan(x) || beats(x, lane[best]) {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Shape that `left` and `right` broadcast to: axes are aligned from the right and each pair
+/// must either match or contain a 1.
+pub fn broadcast_shape(left: &[usize], right: &[usize]) -> Vec<usize> {
+    let rank = left.len().max(right.len());
+    let dim = |shape: &[usize], axis: usize| match (axis + shape.len()).checked_sub(rank) {
+        Some(i) => shape[i],
+        None => 1,
+    };
+    (0..rank)
+        .map(|axis| match (dim(left, axis), dim(right, axis)) {
+            (m, n) if m == n => m,
+            (1, n) => n,
+            (m, 1) => m,
+            _ => panic!("Shapes {:?} and {:?} cannot be broadcast", left, right),
+        })
+        .collect()
+}
+
 // TODO: clean up this dumping ground
 #[allow(dead_code)]
-impl Tensor {
+impl<T: Element> Tensor<T> {
     pub fn named(mut self, name: String) -> Self {
         self.name = name;
         self
     }
 
-    pub fn metadata(&self) -> Rc<RefCell<Gradient>> {
+    pub fn metadata(&self) -> Rc<RefCell<Gradient<T>>> {
         self.gradient.clone()
     }
 
-    pub fn from_vector(data: Vec<Vec<f64>>) -> Tensor {
+    pub fn from_shape(shape: &[usize], data: Vec<T>) -> Tensor<T> {
+        Tensor::from_storage(shape, Storage::from(data))
+    }
+
+    /// Tensor of `shape` over `data`, which may be shared with other tensors.
+    pub fn from_storage(shape: &[usize], data: Storage<T>) -> Tensor<T> {
+        let expected: usize = shape.iter().product();
+        assert_eq!(
+            expected,
+            data.len(),
+            "Shape {:?} needs {} elements, got {}",
+            shape,
+            expected,
+            data.len()
+        );
         Tensor {
-            data: data.clone(),
-            size: Tensor::get_size(&data),
+            data,
+            shape: shape.to_vec(),
+            strides: contiguous_strides(shape),
             ..Tensor::default()
         }
     }
 
-    pub fn from_array(array: &[&[f64]]) -> Tensor {
+    pub fn from_vector(data: Vec<Vec<T>>) -> Tensor<T> {
+        let (m, n) = Tensor::get_size(&data);
+        assert!(
+            data.iter().all(|row| row.len() == n),
+            "All rows must have the same length"
+        );
+        Tensor::from_shape(&[m, n], data.into_iter().flatten().collect())
+    }
+
+    pub fn from_array(array: &[&[T]]) -> Tensor<T> {
         Tensor::from_vector(array.iter().map(|&row| row.to_vec()).collect::<Vec<_>>())
     }
 
-    pub fn empty() -> Tensor {
-        let data = vec![vec![]];
-        Tensor {
-            data: data.clone(),
-            size: (1, 0), // TODO: ..?
-            ..Tensor::default()
-        }
+    pub fn empty() -> Tensor<T> {
+        Tensor::from_shape(&[1, 0], vec![]) // TODO: ..?
     }
 
-    pub fn singleton(value: f64) -> Tensor {
+    /// Rank-0 tensor holding `value`, which broadcasts against any shape.
+    pub fn scalar(value: T) -> Tensor<T> {
+        Tensor::from_shape(&[], vec![value])
+    }
+
+    pub fn singleton(value: T) -> Tensor<T> {
         Tensor::fill(1, 1, value)
     }
 
-    pub fn fill(m: usize, n: usize, value: f64) -> Tensor {
-        let data = vec![vec![value; n]; m];
-        Tensor::from_vector(data)
+    pub fn full(shape: &[usize], value: T) -> Tensor<T> {
+        let length = shape.iter().product();
+        Tensor::from_shape(shape, vec![value; length])
+    }
+
+    pub fn fill(m: usize, n: usize, value: T) -> Tensor<T> {
+        Tensor::full(&[m, n], value)
     }
 
-    pub fn zeros(m: usize, n: usize) -> Tensor {
-        Tensor::fill(m, n, 0.0)
+    pub fn zeros(m: usize, n: usize) -> Tensor<T> {
+        Tensor::fill(m, n, T::from_f64(0.0))
     }
 
-    pub fn ones(m: usize, n: usize) -> Tensor {
-        Tensor::fill(m, n, 1.0)
+    pub fn ones(m: usize, n: usize) -> Tensor<T> {
+        Tensor::fill(m, n, T::from_f64(1.0))
     }
 
-    pub fn num_elements(&self) -> i32 {
-        let (m, n) = self.size;
-        (m as i32) * (n as i32)
+    pub fn zeros_like(tensor: &Tensor<T>) -> Tensor<T> {
+        Tensor::full(&tensor.shape, T::from_f64(0.0))
     }
 
-    pub fn item(&self) -> f64 {
-        match self.size {
-            (1, 1) => self[0][0],
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn item(&self) -> T {
+        match self.data.len() {
+            1 => self.data[0],
             _ => panic!("Cannot call item() on a tensor with non-unit size"),
         }
     }
 
-    // Operations
-    // pub fn concat(&self, x: &Tensor) -> Tensor {
-    //     let (m, _) = self.size();
-    //     let (x_m, _) = x.size();
-    //     if x_m != m {
-    //         panic!("Expected {} got {}", m, x_m);
-    //     }
-    //
-    //     let mut result = self.clone(); // TODO: is data the same pointer?
-    //     for row in x.data.iter() {
-    //         result.data.push(row.to_vec());
-    //     }
-    //     result
-    // }
-
-    fn get_size(data: &[Vec<f64>]) -> (usize, usize) {
+    /// Position in `data` of the element at `index`.
+    pub fn offset(&self, index: &[usize]) -> usize {
+        assert_eq!(
+            index.len(),
+            self.rank(),
+            "Expected {} indices, got {}",
+            self.rank(),
+            index.len()
+        );
+        index
+            .iter()
+            .zip(self.shape.iter().zip(self.strides.iter()))
+            .map(|(&i, (&dim, &stride))| {
+                assert!(i < dim, "Index out of bounds");
+                i * stride
+            })
+            .sum()
+    }
+
+    /// Multi-dimensional index of the `position`th element in row-major order.
+    pub fn unravel(&self, position: usize) -> Vec<usize> {
+        unravel(&self.shape, position)
+    }
+
+    /// Offsets of the first element of every 1-D lane along `axis`. Consecutive elements of a
+    /// lane are `self.strides[axis]` apart.
+    pub fn lanes(&self, axis: usize) -> Vec<usize> {
+        assert!(
+            axis < self.rank(),
+            "Axis {} out of range for shape {:?}",
+            axis,
+            self.shape
+        );
+        let mut shape = self.shape.clone();
+        shape[axis] = 1;
+        (0..shape.iter().product())
+            .map(|position| {
+                unravel(&shape, position)
+                    .iter()
+                    .zip(self.strides.iter())
+                    .map(|(i, stride)| i * stride)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Elements of the lane along `axis` starting at offset `start`.
+    pub fn lane(&self, axis: usize, start: usize) -> Vec<T> {
+        let stride = self.strides[axis];
+        (0..self.shape[axis])
+            .map(|i| self.data[start + i * stride])
+            .collect()
+    }
+
+    /// Replaces every lane along `axis` with `fun` applied to it.
+    pub fn map_lanes(&self, axis: usize, fun: impl Fn(&[T]) -> Vec<T>) -> Tensor<T> {
+        // `lanes` validates `axis`, so find them before indexing `strides`
+        let lanes = self.lanes(axis);
+        let stride = self.strides[axis];
+        let mut data = self.data.clone();
+        for start in lanes {
+            for (i, value) in fun(&self.lane(axis, start)).into_iter().enumerate() {
+                data[start + i * stride] = value;
+            }
+        }
+        Tensor::from_storage(&self.shape, data)
+    }
+
+    /// Shape after reducing over `axis`, which is kept with size 1 if `keepdim`.
+    pub fn reduced_shape(&self, axis: usize, keepdim: bool) -> Vec<usize> {
+        let mut shape = self.shape.clone();
+        match keepdim {
+            true => shape[axis] = 1,
+            false => {
+                shape.remove(axis);
+            }
+        }
+        shape
+    }
+
+    /// Collapses every lane along `axis` to `fun` of its elements.
+    pub fn reduce_lanes<U: Element>(
+        &self,
+        axis: usize,
+        keepdim: bool,
+        fun: impl Fn(&[T]) -> U,
+    ) -> Tensor<U> {
+        let data = self
+            .lanes(axis)
+            .into_iter()
+            .map(|start| fun(&self.lane(axis, start)))
+            .collect();
+        Tensor::from_shape(&self.reduced_shape(axis, keepdim), data)
+    }
+
+    /// Panics unless `axis` has elements to pick from, as `operation` has no result otherwise.
+    pub(crate) fn assert_nonempty_axis(&self, operation: &str, axis: usize) {
+        assert!(
+            self.shape.get(axis) != Some(&0),
+            "Cannot take {} over empty axis {} of shape {:?}",
+            operation,
+            axis,
+            self.shape
+        );
+    }
+
+    /// Index along `axis` of the largest element of each lane (the first one on ties).
+    pub fn argmax(&self, axis: usize, keepdim: bool) -> Tensor<i64> {
+        self.assert_nonempty_axis("argmax", axis);
+        self.reduce_lanes(axis, keepdim, |lane| {
+            arg_best(lane, |x, best| x > best) as i64
+        })
+    }
+
+    /// Index along `axis` of the smallest element of each lane (the first one on ties).
+    pub fn argmin(&self, axis: usize, keepdim: bool) -> Tensor<i64> {
+        self.assert_nonempty_axis("argmin", axis);
+        self.reduce_lanes(axis, keepdim, |lane| {
+            arg_best(lane, |x, best| x < best) as i64
+        })
+    }
+
+    /// Where `self > other` elementwise, broadcasting. Comparisons aren't differentiable.
+    pub fn gt(&self, other: &Tensor<T>) -> Tensor<bool> {
+        self.zip_with(other, |x, y| x > y)
+    }
+
+    /// Where `self < other`.
+    pub fn lt(&self, other: &Tensor<T>) -> Tensor<bool> {
+        self.zip_with(other, |x, y| x < y)
+    }
+
+    /// Where `self >= other`.
+    pub fn ge(&self, other: &Tensor<T>) -> Tensor<bool> {
+        self.zip_with(other, |x, y| x >= y)
+    }
+
+    /// Where `self <= other`.
+    pub fn le(&self, other: &Tensor<T>) -> Tensor<bool> {
+        self.zip_with(other, |x, y| x <= y)
+    }
+
+    /// Where `self == other`. Compare whole tensors with `==` instead.
+    pub fn eq(&self, other: &Tensor<T>) -> Tensor<bool> {
+        self.zip_with(other, |x, y| x == y)
+    }
+
+    /// Elementwise cast to another element type. The result is a new leaf, outside the graph.
+    pub fn to_dtype<U: Element>(&self) -> Tensor<U> {
+        self.map(|x| U::from_f64(x.into_f64()))
+    }
+
+    fn get_size(data: &[Vec<T>]) -> (usize, usize) {
         match data.is_empty() {
             true => (0, 0),
             false => match data[0].is_empty() {
@@ -112,113 +343,186 @@ impl Tensor {
         }
     }
 
-    pub fn transpose(&self) -> Tensor {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; m]; n];
+    pub fn apply(&self, fun: impl Fn(&[usize], &Tensor<T>) -> T) -> Tensor<T> {
+        let data = (0..self.data.len())
+            .map(|position| fun(&self.unravel(position), self))
+            .collect();
 
-        // TODO: how to do this with apply?
-        (0..n).for_each(|i| {
-            (0..m).for_each(|j| {
-                data[i][j] = self[j][i];
-            });
-        });
+        Tensor::from_shape(&self.shape, data)
+    }
 
-        Tensor::from_vector(data)
+    pub fn map<U: Element>(&self, fun: impl Fn(T) -> U) -> Tensor<U> {
+        Tensor::from_shape(&self.shape, self.data.iter().map(|&x| fun(x)).collect())
     }
 
-    pub fn apply(&self, fun: impl Fn(usize, usize, &Tensor) -> f64) -> Tensor {
-        // TODO: make this work for N-dimensional tensors
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
+    /// Applies `fun` elementwise, broadcasting `self` and `other` to a common shape.
+    pub fn zip_with<U: Element, V: Element>(
+        &self,
+        other: &Tensor<U>,
+        fun: impl Fn(T, U) -> V,
+    ) -> Tensor<V> {
+        let shape = broadcast_shape(&self.shape, &other.shape);
+        let (left, right) = (self.broadcast_to(&shape), other.broadcast_to(&shape));
+        let data = left
+            .data
+            .iter()
+            .zip(right.data.iter())
+            .map(|(&x, &y)| fun(x, y))
+            .collect();
+        Tensor::from_shape(&shape, data)
+    }
 
-        (0..m).for_each(|i| {
-            (0..n).for_each(|j| {
-                data[i][j] = fun(i, j, self);
-            });
-        });
+    /// Strides that read `self` as if it had `shape`, repeating broadcast axes with stride 0.
+    fn broadcast_strides(&self, shape: &[usize]) -> Vec<usize> {
+        assert!(
+            self.rank() <= shape.len(),
+            "Cannot broadcast {:?} to {:?}",
+            self.shape,
+            shape
+        );
+        let padding = shape.len() - self.rank();
+        (0..shape.len())
+            .map(|axis| match axis.checked_sub(padding) {
+                None => 0,
+                Some(i) if self.shape[i] == shape[axis] => self.strides[i],
+                Some(i) if self.shape[i] == 1 => 0,
+                Some(_) => panic!("Cannot broadcast {:?} to {:?}", self.shape, shape),
+            })
+            .collect()
+    }
 
-        Tensor::from_vector(data)
+    /// Copies of `self` expanded to `shape` following NumPy broadcasting rules.
+    pub fn broadcast_to(&self, shape: &[usize]) -> Tensor<T> {
+        if self.shape == shape {
+            return Tensor::from_storage(shape, self.data.clone());
+        }
+        let strides = self.broadcast_strides(shape);
+        let data = (0..shape.iter().product())
+            .map(|position| {
+                let index = unravel(shape, position);
+                let offset: usize = index.iter().zip(strides.iter()).map(|(i, s)| i * s).sum();
+                self.data[offset]
+            })
+            .collect();
+        Tensor::from_shape(shape, data)
     }
 }
 
-impl Clone for Tensor {
-    fn clone(&self) -> Self {
-        let (m, n) = self.size;
-        let mut data = vec![vec![0.0; n]; m];
-        for i in 0..m {
-            for j in 0..n {
-                data[i][j] = self[i][j];
-            }
+impl<T: Float> Tensor<T> {
+    /// Sums `self` over the axes that were broadcast when expanding `shape` to `self.shape`.
+    /// This is the adjoint of `broadcast_to`, used to route gradients back to an operand.
+    pub fn sum_to(&self, shape: &[usize]) -> Tensor<T> {
+        if self.shape == shape {
+            return Tensor::from_storage(shape, self.data.clone());
         }
+        let mut result = Tensor::full(shape, T::zero());
+        let strides = result.broadcast_strides(&self.shape);
+        (0..self.data.len()).for_each(|position| {
+            let index = self.unravel(position);
+            let offset: usize = index.iter().zip(strides.iter()).map(|(i, s)| i * s).sum();
+            result.data[offset] += self.data[position];
+        });
+        result
+    }
+}
+
+impl<T: Element> Clone for Tensor<T> {
+    fn clone(&self) -> Self {
         Tensor {
             data: self.data.clone(),
-            size: (m, n),
+            shape: self.shape.clone(),
+            strides: self.strides.clone(),
             name: self.name.clone(),
             gradient: Rc::clone(&self.gradient),
         }
     }
 }
 
-impl IndexMut<usize> for Tensor {
+impl<T: Element> IndexMut<usize> for Tensor<T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        let (m, _n) = self.size;
+        assert_eq!(self.rank(), 2, "Row indexing expects a rank-2 tensor");
+        let (m, n) = (self.shape[0], self.shape[1]);
         assert!(index < m, "Index out of bounds");
-        &mut self.data[index]
+        &mut self.data[index * n..(index + 1) * n]
     }
 }
 
-impl Index<usize> for Tensor {
-    type Output = Vec<f64>;
-    fn index(&self, index: usize) -> &Vec<f64> {
-        let (m, _n) = self.size;
+impl<T: Element> Index<usize> for Tensor<T> {
+    type Output = [T];
+    fn index(&self, index: usize) -> &[T] {
+        assert_eq!(self.rank(), 2, "Row indexing expects a rank-2 tensor");
+        let (m, n) = (self.shape[0], self.shape[1]);
         assert!(index < m, "Index out of bounds");
-        &self.data[index]
+        &self.data[index * n..(index + 1) * n]
+    }
+}
+
+impl<T: Element> Index<&[usize]> for Tensor<T> {
+    type Output = T;
+    fn index(&self, index: &[usize]) -> &T {
+        &self.data[self.offset(index)]
+    }
+}
+
+impl<T: Element> IndexMut<&[usize]> for Tensor<T> {
+    fn index_mut(&mut self, index: &[usize]) -> &mut T {
+        let offset = self.offset(index);
+        &mut self.data[offset]
+    }
+}
+
+impl<T: Element, const N: usize> Index<[usize; N]> for Tensor<T> {
+    type Output = T;
+    fn index(&self, index: [usize; N]) -> &T {
+        &self[&index[..]]
+    }
+}
+
+impl<T: Element, const N: usize> IndexMut<[usize; N]> for Tensor<T> {
+    fn index_mut(&mut self, index: [usize; N]) -> &mut T {
+        &mut self[&index[..]]
     }
 }
 
-impl AddAssign<&Tensor> for Tensor {
-    fn add_assign(&mut self, right: &Tensor) {
-        assert_eq!(self.size, right.size, "Sizes must be equal");
-        let (m, n) = self.size;
-        (0..m).for_each(|i| (0..n).for_each(|j| self.data[i][j] += right[i][j]));
+impl<T: Float> AddAssign<&Tensor<T>> for Tensor<T> {
+    fn add_assign(&mut self, right: &Tensor<T>) {
+        assert_eq!(self.shape, right.shape, "Sizes must be equal");
+        simd::add_assign(&mut self.data, &right.data);
     }
 }
 
-impl SubAssign<&Tensor> for Tensor {
-    fn sub_assign(&mut self, right: &Tensor) {
-        assert_eq!(self.size, right.size, "Sizes must be equal");
-        let (m, n) = self.size;
-        (0..m).for_each(|i| (0..n).for_each(|j| self.data[i][j] -= right[i][j]));
+impl<T: Float> SubAssign<&Tensor<T>> for Tensor<T> {
+    fn sub_assign(&mut self, right: &Tensor<T>) {
+        assert_eq!(self.shape, right.shape, "Sizes must be equal");
+        simd::sub_assign(&mut self.data, &right.data);
     }
 }
 
-impl Debug for Tensor {
+impl<T: Element> Debug for Tensor<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self::Display::fmt(&self, f)
     }
 }
 
-impl Display for Tensor {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let result = self
-            .data
-            .iter()
-            .map(|row| {
-                format!(
-                    "[{}]",
-                    row.iter()
-                        .map(|&x| x.to_string())
-                        .collect::<Vec<String>>()
-                        .join(" ")
-                )
-            })
-            .collect::<Vec<String>>()
-            .join(" ");
+fn format_axis<T: Element>(tensor: &Tensor<T>, axis: usize, offset: usize) -> String {
+    if axis == tensor.rank() {
+        return tensor.data[offset].to_string();
+    }
+    let entries = (0..tensor.shape[axis])
+        .map(|i| format_axis(tensor, axis + 1, offset + i * tensor.strides[axis]))
+        .collect::<Vec<String>>()
+        .join(" ");
+    format!("[{}]", entries)
+}
 
-        write!(f, "[{}]", result)?;
+impl<T: Element> Display for Tensor<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_axis(self, 0, 0))?;
         Ok(())
     }
 }
+
+#[allow(dead_code)]
 trait ToGraphviz {
     fn to_dot() -> String;
 }
@@ -3,7 +3,15 @@ mod gradient_tests {
     use core::f64;
 
     use approx::assert_relative_eq;
-    use llm_rs::{data::TestData, operations::Differentiable, tensor::Tensor};
+    use llm_rs::{
+        data::TestData,
+        operations::{
+            check::{gradcheck, Tolerance},
+            is_grad_enabled, no_grad, Differentiable, GeluApproximation, GradientOperation,
+            NoGradGuard,
+        },
+        tensor::Tensor,
+    };
 
     #[test]
     fn simple_gradient_descent() {
@@ -70,7 +78,7 @@ mod gradient_tests {
                 weights.set_grad(Tensor::singleton(0.0));
                 bias.set_grad(Tensor::singleton(0.0));
                 let (x, y) = (sample.input, sample.output);
-                let y_pred = &(&*weights * &x) + bias;
+                let y_pred = &(&*weights * &x) + &*bias;
 
                 // Backward pass
                 let loss = &Differentiable::pow(&(y_pred - y.clone()), 2);
@@ -202,4 +210,644 @@ mod gradient_tests {
         assert_eq!(b.grad().item(), -2.0);
         //
     }
+
+    #[test]
+    fn broadcast_add_sums_gradient() {
+        let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0]]).with_grad();
+        let b = Tensor::from_array(&[&[10.0, 20.0]]).with_grad();
+
+        let y = &a - &b;
+        y.set_grad(Tensor::ones(3, 2));
+        y.backward();
+
+        assert_eq!(Tensor::ones(3, 2), a.grad());
+        assert_eq!(Tensor::fill(1, 2, -3.0), b.grad());
+    }
+
+    #[test]
+    fn shared_nodes_propagate_once() {
+        // Each level consumes the previous node twice, so a per-consumer traversal would
+        // visit the leaf 2^40 times. Nothing is named, so labels must stay bounded too.
+        let x = Tensor::singleton(1.0).with_grad();
+        let mut y = x.clone();
+        for _ in 0..40 {
+            y = &y + &y;
+        }
+        assert!(y.name.chars().count() < 200);
+
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        assert_eq!(2f64.powi(40), x.grad().item());
+    }
+
+    #[test]
+    fn shared_node_receives_all_contributions() {
+        // y = (a * b) - (a * b)(a * b) with e = a * b used three times
+        let a = Tensor::singleton(3.0).with_grad();
+        let b = Tensor::singleton(2.0).with_grad();
+        let e = &a * &b;
+        let y = &e - &(&e * &e);
+
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        // dy/de = 1 - 2e = -11
+        assert_eq!(-11.0, e.grad().item());
+        assert_eq!(-22.0, a.grad().item());
+        assert_eq!(-33.0, b.grad().item());
+    }
+
+    #[test]
+    fn deep_graph_backward_does_not_overflow() {
+        let x = Tensor::singleton(1.0).with_grad();
+        let mut y = x.clone();
+        for _ in 0..100_000 {
+            y = -&y;
+        }
+
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        assert_eq!(1.0, x.grad().item());
+    }
+
+    /// Checks the analytic gradient of `sum(w * f(inputs))`, for fixed non-uniform weights `w`,
+    /// against central finite differences. `f` must produce a rank-2 tensor.
+    fn assert_gradients_match(inputs: Vec<Tensor>, f: impl Fn(&[Tensor]) -> Tensor) {
+        let weighted_sum = |inputs: &[Tensor]| {
+            let y = f(inputs);
+            let (m, n) = (y.shape[0], y.shape[1]);
+            let u = Tensor::from_shape(&[1, m], (0..m).map(|i| 1.0 + i as f64).collect());
+            let v = Tensor::from_shape(&[n, 1], (0..n).map(|j| 0.5 - j as f64).collect());
+            &(&u * &y) * &v
+        };
+        gradcheck(weighted_sum, &inputs, Tolerance::default()).unwrap();
+    }
+
+    fn sample() -> Tensor {
+        Tensor::from_array(&[&[0.5, -1.5, 2.0], &[-0.25, 1.25, 3.0]])
+    }
+
+    #[test]
+    fn neg_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| -&x[0]);
+    }
+
+    #[test]
+    fn relu_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].relu());
+    }
+
+    #[test]
+    fn pow_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].pow(3));
+        let zero = Tensor::from_array(&[&[0.0, 2.0]]);
+        assert_gradients_match(vec![zero], |x| x[0].pow(0));
+    }
+
+    #[test]
+    fn mean_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].mean());
+    }
+
+    #[test]
+    fn add_matches_numerical_gradient() {
+        let b = Tensor::from_array(&[&[1.0, 2.0, -3.0]]);
+        assert_gradients_match(vec![sample(), b], |x| &x[0] + &x[1]);
+    }
+
+    #[test]
+    fn sub_matches_numerical_gradient() {
+        let b = Tensor::from_array(&[&[1.0], &[-2.0]]);
+        assert_gradients_match(vec![sample(), b], |x| &x[0] - &x[1]);
+    }
+
+    #[test]
+    fn mul_matches_numerical_gradient() {
+        let b = Tensor::from_array(&[&[1.0, 0.5], &[2.0, -1.0], &[-3.0, 0.25]]);
+        assert_gradients_match(vec![sample(), b], |x| &x[0] * &x[1]);
+    }
+
+    #[test]
+    fn batched_matmul_matches_numerical_gradient() {
+        let a = Tensor::from_shape(&[2, 2, 3], (0..12).map(|x| 0.25 * x as f64 - 1.0).collect());
+        let b = Tensor::from_array(&[&[1.0, 0.5], &[2.0, -1.0], &[-3.0, 0.25]]);
+        assert_gradients_match(vec![a.clone(), b], |x| x[0].matmul(&x[1]).flatten(0, 1));
+
+        let c = Tensor::from_shape(
+            &[2, 1, 3, 2],
+            (0..12).map(|x| 1.5 - 0.5 * x as f64).collect(),
+        );
+        assert_gradients_match(vec![a, c], |x| x[0].matmul(&x[1]).reshape(&[8, 2]));
+    }
+
+    #[test]
+    fn vector_matmul_matches_numerical_gradient() {
+        let v = Tensor::from_shape(&[3], vec![0.5, -1.0, 2.0]);
+        let m = Tensor::from_array(&[&[1.0, 0.5], &[2.0, -1.0], &[-3.0, 0.25]]);
+        assert_gradients_match(vec![v.clone(), m], |x| x[0].matmul(&x[1]).unsqueeze(0));
+        assert_gradients_match(vec![sample(), v.clone()], |x| {
+            x[0].matmul(&x[1]).unsqueeze(1)
+        });
+        assert_gradients_match(vec![v.clone(), v], |x| x[0].matmul(&x[1]).reshape(&[1, 1]));
+    }
+
+    #[test]
+    fn mul_elem_matches_numerical_gradient() {
+        let b = Tensor::from_array(&[&[1.0, 0.5, -2.0]]);
+        assert_gradients_match(vec![sample(), b], |x| x[0].mul_elem(&x[1]));
+    }
+
+    #[test]
+    fn div_matches_numerical_gradient() {
+        let b = Tensor::from_array(&[&[1.0], &[-2.5]]);
+        assert_gradients_match(vec![sample(), b.clone()], |x| &x[0] / &x[1]);
+        assert_gradients_match(vec![b, sample()], |x| &x[0] / &x[1]);
+    }
+
+    #[test]
+    fn scalar_div_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| &x[0] / 4.0);
+    }
+
+    #[test]
+    fn scalar_operations_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| &x[0] + 2.0);
+        assert_gradients_match(vec![sample()], |x| 2.0 + &x[0]);
+        assert_gradients_match(vec![sample()], |x| &x[0] - 2.0);
+        assert_gradients_match(vec![sample()], |x| 2.0 - &x[0]);
+        assert_gradients_match(vec![sample()], |x| &x[0] * 3.0);
+        assert_gradients_match(vec![sample()], |x| 3.0 * &x[0]);
+        assert_gradients_match(vec![sample()], |x| 3.0 / &x[0]);
+    }
+
+    #[test]
+    fn scalar_multiplication_keeps_gradient() {
+        let x = Tensor::from_array(&[&[1.0, 2.0]]).with_grad();
+        let y = (2.0 * &x).mean();
+
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        assert!(y.requires_grad());
+        assert_eq!(Tensor::from_array(&[&[1.0, 1.0]]), x.grad());
+    }
+
+    fn positive_sample() -> Tensor {
+        Tensor::from_array(&[&[0.5, 1.5, 2.0], &[0.25, 1.25, 3.0]])
+    }
+
+    #[test]
+    fn exp_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].exp());
+    }
+
+    #[test]
+    fn ln_matches_numerical_gradient() {
+        assert_gradients_match(vec![positive_sample()], |x| x[0].ln());
+    }
+
+    #[test]
+    fn sqrt_matches_numerical_gradient() {
+        assert_gradients_match(vec![positive_sample()], |x| x[0].sqrt());
+    }
+
+    #[test]
+    fn abs_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].abs());
+    }
+
+    #[test]
+    fn trigonometric_functions_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].sin());
+        assert_gradients_match(vec![sample()], |x| x[0].cos());
+    }
+
+    #[test]
+    fn powf_matches_numerical_gradient() {
+        assert_gradients_match(vec![positive_sample()], |x| x[0].powf(1.5));
+        assert_gradients_match(vec![positive_sample()], |x| x[0].powf(-0.5));
+    }
+
+    #[test]
+    fn sigmoid_and_tanh_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].sigmoid());
+        assert_gradients_match(vec![sample()], |x| x[0].tanh());
+    }
+
+    #[test]
+    fn gelu_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].gelu(GeluApproximation::None));
+        assert_gradients_match(vec![sample()], |x| x[0].gelu(GeluApproximation::Tanh));
+    }
+
+    #[test]
+    fn leaky_relu_and_elu_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].leaky_relu(0.1));
+        assert_gradients_match(vec![sample()], |x| x[0].elu(1.5));
+    }
+
+    #[test]
+    fn silu_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].silu());
+    }
+
+    #[test]
+    fn softmax_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].softmax(0));
+        assert_gradients_match(vec![sample()], |x| x[0].softmax(1));
+    }
+
+    #[test]
+    fn log_softmax_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].log_softmax(0));
+        assert_gradients_match(vec![sample()], |x| x[0].log_softmax(1));
+    }
+
+    #[test]
+    fn sum_and_mean_along_axis_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].sum(0, true));
+        assert_gradients_match(vec![sample()], |x| x[0].mean_axis(1, true));
+    }
+
+    #[test]
+    fn max_and_min_route_gradient_to_selected_element() {
+        assert_gradients_match(vec![sample()], |x| x[0].max(1, true));
+        assert_gradients_match(vec![sample()], |x| x[0].min(0, true));
+
+        let x = sample().with_grad();
+        let y = x.max(1, false);
+        y.set_grad(Tensor::from_shape(&[2], vec![1.0, 1.0]));
+        y.backward();
+        assert_eq!(
+            Tensor::from_array(&[&[0.0, 0.0, 1.0], &[0.0, 0.0, 1.0]]),
+            x.grad()
+        );
+    }
+
+    #[test]
+    fn var_and_std_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].var(1, true, true));
+        assert_gradients_match(vec![sample()], |x| x[0].var(0, false, true));
+        assert_gradients_match(vec![sample()], |x| x[0].std(1, true, true));
+    }
+
+    #[test]
+    fn reshapes_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].reshape(&[3, 2]));
+        assert_gradients_match(vec![sample()], |x| x[0].reshape(&[3, 1, 2]).flatten(1, 2));
+        assert_gradients_match(vec![sample()], |x| x[0].unsqueeze(0).squeeze(0));
+    }
+
+    #[test]
+    fn permute_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].permute(&[1, 0]));
+        assert_gradients_match(vec![sample()], |x| {
+            x[0].reshape(&[2, 3, 1])
+                .permute(&[2, 0, 1])
+                .squeeze(0)
+                .mul_elem(&sample())
+        });
+    }
+
+    #[test]
+    fn transpose_matches_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].transpose(0, 1));
+        assert_gradients_match(vec![sample()], |x| {
+            x[0].unsqueeze(1).transpose(0, 2).squeeze(1)
+        });
+    }
+
+    #[test]
+    fn transposed_weights_keep_gradient() {
+        let x = Tensor::from_array(&[&[1.0, 2.0, 3.0]]);
+        let w = Tensor::from_array(&[&[0.5, -1.0, 2.0], &[1.5, 0.0, -0.5]]).with_grad();
+
+        let y = &x * &w.transpose(0, 1);
+        y.set_grad(Tensor::from_array(&[&[1.0, 2.0]]));
+        y.backward();
+
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]]),
+            w.grad()
+        );
+    }
+
+    #[test]
+    fn selections_match_numerical_gradient() {
+        assert_gradients_match(vec![sample()], |x| x[0].narrow(1, 1, 2));
+        assert_gradients_match(vec![sample()], |x| x[0].index_select(1, &[2, 0, 2]));
+        assert_gradients_match(vec![sample()], |x| {
+            x[0].masked_select(&Tensor::from_array(&[&[true, false, true]]))
+                .unsqueeze(0)
+        });
+    }
+
+    #[test]
+    fn index_select_scatters_gradient_back() {
+        let x = sample().with_grad();
+
+        let y = x.index_select(0, &[1, 1, 0]);
+        y.set_grad(Tensor::ones(3, 3));
+        y.backward();
+
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 1.0, 1.0], &[2.0, 2.0, 2.0]]),
+            x.grad()
+        );
+    }
+
+    #[test]
+    fn cat_and_stack_match_numerical_gradient() {
+        let other = Tensor::from_array(&[&[1.0, -2.0], &[0.75, 0.5]]);
+        assert_gradients_match(vec![sample(), other], |x| Tensor::cat(x, 1));
+        assert_gradients_match(vec![sample(), sample()], |x| {
+            Tensor::stack(x, 0).flatten(0, 1)
+        });
+    }
+
+    #[test]
+    fn split_pieces_route_gradient_back() {
+        assert_gradients_match(vec![sample()], |x| {
+            let pieces = x[0].split(&[1, 2], 1);
+            Tensor::cat(&[pieces[1].clone(), pieces[0].clone()], 1)
+        });
+        assert_gradients_match(vec![sample()], |x| x[0].chunk(2, 1)[1].clone());
+    }
+
+    #[test]
+    fn gather_matches_numerical_gradient() {
+        let index = Tensor::from_array(&[&[2, 0], &[1, 1]]);
+        assert_gradients_match(vec![sample()], |x| x[0].gather(1, &index));
+    }
+
+    #[test]
+    fn scatter_add_matches_numerical_gradient() {
+        let index = Tensor::from_array(&[&[1, 0, 1]]);
+        let src = Tensor::from_array(&[&[0.5, 2.0, -1.0], &[3.0, 3.0, 3.0]]);
+        assert_gradients_match(vec![sample(), src], |x| x[0].scatter_add(0, &index, &x[1]));
+    }
+
+    #[test]
+    fn gather_picks_class_scores() {
+        // Negative log-likelihood of the labelled classes, as in cross-entropy
+        let logits = sample().with_grad();
+        let labels = Tensor::from_array(&[&[2], &[0]]);
+
+        let loss = -&logits.log_softmax(1).gather(1, &labels).mean();
+        loss.set_grad(Tensor::singleton(1.0));
+        loss.backward();
+
+        let p = logits.softmax(1);
+        assert_relative_eq!(
+            p[[0, 2]] / 2.0 - 0.5,
+            logits.grad()[[0, 2]],
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(p[[1, 1]] / 2.0, logits.grad()[[1, 1]], epsilon = 1e-12);
+    }
+
+    #[test]
+    fn where_matches_numerical_gradient() {
+        let condition = sample().gt(&Tensor::scalar(0.0));
+        let row = Tensor::from_array(&[&[1.0, -2.0, 0.5]]);
+        assert_gradients_match(vec![sample(), row], |x| {
+            Tensor::where_(&condition, &x[0], &x[1])
+        });
+        assert_gradients_match(vec![sample()], |x| {
+            x[0].masked_fill(&Tensor::from_array(&[&[false, true, false]]), 5.0)
+        });
+    }
+
+    #[test]
+    fn where_builds_piecewise_functions() {
+        // Clip to [-1, 1]; the gradient vanishes where the bounds are taken
+        let x = sample().with_grad();
+        let (low, high) = (Tensor::scalar(-1.0), Tensor::scalar(1.0));
+
+        let clipped = Tensor::where_(&x.gt(&high), &high, &Tensor::where_(&x.lt(&low), &low, &x));
+        clipped.set_grad(Tensor::ones(2, 3));
+        clipped.backward();
+
+        assert_eq!(
+            Tensor::from_array(&[&[0.5, -1.0, 1.0], &[-0.25, 1.0, 1.0]]),
+            clipped
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 0.0, 0.0], &[1.0, 0.0, 0.0]]),
+            x.grad()
+        );
+    }
+
+    #[test]
+    fn f32_tensors_are_differentiable() {
+        let x = Tensor::from_array(&[&[0.5f32, -1.5], &[2.0, 0.25]]).with_grad();
+        let w = Tensor::from_array(&[&[1.0f32], &[-2.0]]).with_grad();
+
+        let y = (&(&x * &w).tanh() * 3.0f32).mean();
+        y.set_grad(Tensor::singleton(1.0f32));
+        y.backward();
+
+        let z = &x * &w;
+        for i in 0..2 {
+            let expected = 3.0 / 2.0 * (1.0 - z[[i, 0]].tanh().powi(2));
+            assert_relative_eq!(expected * w[[1, 0]], x.grad()[[i, 1]], epsilon = 1e-6);
+        }
+        assert_eq!(vec![2, 1], w.grad().shape);
+    }
+
+    #[test]
+    fn graph_shares_operand_and_output_storage() {
+        let x = Tensor::from_array(&[&[1.0, -2.0], &[3.0, -4.0]]).with_grad();
+        let y = x.relu();
+
+        assert!(y.last().data.shares(&y.data));
+        let node = y.gradient.borrow();
+        match &node.operation {
+            GradientOperation::ReLU(input) => assert!(input.data.shares(&x.data)),
+            _ => panic!("Expected a ReLU node"),
+        }
+    }
+
+    #[test]
+    fn losses_sharing_a_node_accumulate_only_their_own_grads() {
+        let x = Tensor::from_array(&[&[1.0]]).with_grad();
+        let h: Tensor = &x * 3.0;
+        let l1 = h.mean();
+        let l2 = (&h * 2.0).mean();
+
+        l1.set_grad(Tensor::singleton(1.0));
+        l1.backward();
+        l2.set_grad(Tensor::singleton(1.0));
+        l2.backward();
+        assert_eq!(vec![9.0], x.grad().data);
+        assert_eq!(vec![2.0], h.grad().data);
+
+        // Repeating a pass adds its grads to the leaves once more
+        l2.backward();
+        assert_eq!(vec![15.0], x.grad().data);
+    }
+
+    #[test]
+    fn updated_parameter_sends_current_value_to_its_input() {
+        let mut w = Tensor::from_array(&[&[1.0]]).with_grad();
+        let x = Tensor::from_array(&[&[3.0]]).with_grad();
+        w -= &Tensor::from_array(&[&[-1.0]]);
+
+        let y = &x * &w;
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        assert_eq!(vec![2.0], x.grad().data);
+        assert_eq!(vec![3.0], w.grad().data);
+    }
+
+    #[test]
+    fn updated_operation_output_sends_current_value_to_its_input() {
+        let a = Tensor::from_array(&[&[1.0]]).with_grad();
+        let c10 = Tensor::from_array(&[&[10.0]]);
+        let w = Tensor::from_array(&[&[1.0]]).with_grad();
+        let mut h = &a + &a;
+        h += &c10;
+
+        let z = h.mul_elem(&w);
+        z.set_grad(Tensor::singleton(1.0));
+        z.backward();
+
+        assert_eq!(vec![12.0], z.data);
+        assert_eq!(vec![12.0], w.grad().data);
+    }
+
+    #[test]
+    fn mean_returns_single_element() {
+        let y = sample().mean();
+
+        assert_eq!(vec![1, 1], y.shape);
+        assert_relative_eq!(5.0 / 6.0, y.item());
+    }
+
+    #[test]
+    fn mean_of_low_rank_tensors_backpropagates() {
+        let v = Tensor::from_shape(&[4], vec![1.0, 2.0, 3.0, 4.0]).with_grad();
+        let y = v.mean();
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+        assert_eq!(vec![0.25; 4], v.grad().data);
+
+        let s = Tensor::scalar(3.0).with_grad();
+        let y = s.mean();
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+        assert_eq!(vec![1.0], s.grad().data);
+
+        let logits = Tensor::from_array(&[&[1.0, 2.0], &[3.0, -1.0]]).with_grad();
+        let loss = logits.log_softmax(1).sum(1, false).mean();
+        loss.set_grad(Tensor::singleton(1.0));
+        loss.backward();
+        assert_eq!(vec![2, 2], logits.grad().shape);
+    }
+
+    #[test]
+    fn gradcheck_reports_worst_element_per_input() {
+        let a = Tensor::from_array(&[&[1.0, -2.0]]);
+        let b = Tensor::from_array(&[&[3.0], &[4.0]]);
+
+        let report = gradcheck(|x| &x[0] * &x[1], &[a, b], Tolerance::default()).unwrap();
+
+        assert_eq!(2, report.worst.len());
+        let worst = report.worst[1].as_ref().unwrap();
+        assert_eq!(1, worst.input);
+        assert!(worst.passed);
+        assert_relative_eq!(worst.analytic, worst.numeric, max_relative = 1e-6);
+    }
+
+    #[test]
+    fn gradcheck_flags_mismatched_gradient() {
+        // ReLU's subgradient at 0 is taken to be 1, but central differences see a slope of 1/2
+        let a = Tensor::from_array(&[&[1.0, 0.0, -1.0]]);
+
+        let report = gradcheck(|x| x[0].relu().mean(), &[a], Tolerance::default()).unwrap_err();
+
+        assert!(!report.passed());
+        let worst = report.worst[0].as_ref().unwrap();
+        assert_eq!(vec![0, 1], worst.index);
+        assert_relative_eq!(1.0 / 3.0, worst.analytic);
+        assert_relative_eq!(1.0 / 6.0, worst.numeric, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn no_grad_skips_graph_construction() {
+        let x = Tensor::singleton(3.0).with_grad();
+
+        let y = no_grad(|| &(&x * &x) + &x);
+
+        assert_eq!(12.0, y.item());
+        assert!(!y.requires_grad());
+        assert!(matches!(
+            y.gradient.borrow().operation,
+            GradientOperation::None
+        ));
+        // Leaves keep their grad buffers
+        assert!(x.has_grad());
+    }
+
+    #[test]
+    fn no_grad_guard_restores_previous_mode() {
+        assert!(is_grad_enabled());
+        {
+            let _outer = NoGradGuard::new();
+            {
+                let _inner = NoGradGuard::new();
+                assert!(!is_grad_enabled());
+            }
+            assert!(!is_grad_enabled());
+        }
+        assert!(is_grad_enabled());
+
+        let x = Tensor::singleton(3.0).with_grad();
+        assert!((-&x).requires_grad());
+    }
+
+    #[test]
+    fn requires_grad_propagates_from_any_input() {
+        let x = Tensor::singleton(2.0).with_grad();
+        let c = Tensor::singleton(3.0);
+
+        assert!(x.requires_grad());
+        assert!(!c.requires_grad());
+        assert!((&x * &c).requires_grad());
+        assert!((&c * &x).requires_grad());
+        assert!(!(&c * &c).requires_grad());
+    }
+
+    #[test]
+    fn constants_do_not_receive_gradients() {
+        let x = Tensor::singleton(2.0).with_grad();
+        let c = Tensor::singleton(3.0);
+        let y = &x * &c;
+
+        y.set_grad(Tensor::singleton(1.0));
+        y.backward();
+
+        assert_eq!(3.0, x.grad().item());
+        assert!(!c.has_grad());
+    }
+
+    #[test]
+    fn detach_cuts_the_graph() {
+        let x = Tensor::singleton(2.0).with_grad();
+        let y = &x * &x;
+        let z = &y.detach() * &x;
+
+        assert_eq!(y, y.detach());
+        assert!(!y.detach().requires_grad());
+
+        z.set_grad(Tensor::singleton(1.0));
+        z.backward();
+
+        // Only the direct path through x contributes: dz/dx = y = 4
+        assert_eq!(4.0, x.grad().item());
+        assert!(!y.has_grad());
+    }
 }
@@ -3,8 +3,8 @@ mod nn_tests {
     use approx::assert_relative_eq;
     use llm_rs::{
         data::TestData,
-        nn::{Linear, Model, Module, ReLU},
-        operations::Differentiable,
+        nn::{LeakyReLU, Linear, Model, Module, ReLU, SiLU, Sigmoid, Tanh, ELU, GELU},
+        operations::{Differentiable, GeluApproximation},
         optimizer::{Optimizer, StochasticGradientDescent},
         tensor::Tensor,
     };
@@ -108,4 +108,68 @@ mod nn_tests {
             // assert_relative_eq!(prediction.item(), y.item(), max_relative = 1e-5);
         }
     }
+
+    #[test]
+    fn linear_accepts_batches() {
+        let layer = Linear::new(2, 3);
+        let x = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0], &[7.0, 8.0]]);
+
+        let y = layer.forward(x);
+        assert_eq!(vec![4, 3], y.shape);
+        assert_eq!(
+            Tensor::from_array(&[&[4.0; 3], &[8.0; 3], &[12.0; 3], &[16.0; 3]]),
+            y
+        );
+
+        y.set_grad(Tensor::ones(4, 3));
+        y.backward();
+
+        let bias = layer.bias.borrow();
+        assert_eq!(Tensor::fill(1, 3, 4.0), bias.grad());
+        let weights = layer.weights.borrow();
+        assert_eq!(
+            Tensor::from_array(&[&[16.0; 3], &[20.0; 3]]),
+            weights.grad()
+        );
+    }
+
+    #[test]
+    fn activation_modules_apply_their_functions() {
+        let x = || Tensor::from_array(&[&[-1.0, 0.0, 2.0]]);
+
+        assert_eq!(x().sigmoid(), Sigmoid {}.forward(x()));
+        assert_eq!(0.5, Sigmoid {}.forward(Tensor::singleton(0.0)).item());
+        assert_eq!(x().map(f64::tanh), Tanh {}.forward(x()));
+        assert_eq!(
+            Tensor::from_array(&[&[-0.1, 0.0, 2.0]]),
+            LeakyReLU { slope: 0.1 }.forward(x())
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[2.0 * (-1f64).exp_m1(), 0.0, 2.0]]),
+            ELU { alpha: 2.0 }.forward(x())
+        );
+        assert_eq!(x().silu(), SiLU {}.forward(x()));
+
+        let exact = GELU {
+            approximation: GeluApproximation::None,
+        };
+        let approximate = GELU {
+            approximation: GeluApproximation::Tanh,
+        };
+        assert_relative_eq!(
+            0.8413447,
+            exact.forward(Tensor::singleton(1.0)).item(),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            0.8411920,
+            approximate.forward(Tensor::singleton(1.0)).item(),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            -0.0455003,
+            exact.forward(Tensor::singleton(-2.0)).item(),
+            epsilon = 1e-6
+        );
+    }
 }
@@ -1,24 +1,27 @@
 #[cfg(test)]
-
 mod tensor_tests {
-    use llm_rs::{operations::Differentiable, tensor::Tensor};
+    use approx::assert_relative_eq;
+    use llm_rs::{
+        operations::{matmul, simd, Differentiable, GeluApproximation},
+        tensor::{broadcast_shape, Tensor},
+    };
 
     #[test]
     fn from_vector_sets_size() {
         let a = Tensor::from_vector(vec![vec![1.0, 2.0]]);
-        assert_eq!((1, 2), a.size);
+        assert_eq!(vec![1, 2], a.shape);
     }
 
     #[test]
     fn fill_sets_size() {
         let a = Tensor::fill(1, 2, 0.0);
-        assert_eq!((1, 2), a.size);
+        assert_eq!(vec![1, 2], a.shape);
     }
 
     #[test]
     fn from_array_sets_size() {
         let a = Tensor::from_array(&[&[1.0, 2.0]]);
-        assert_eq!((1, 2), a.size);
+        assert_eq!(vec![1, 2], a.shape);
     }
 
     #[test]
@@ -27,7 +30,7 @@ mod tensor_tests {
         a[0][0] = 5.0;
 
         assert_eq!(5.0, a[0][0]);
-        assert_eq!((2, 2), a.size);
+        assert_eq!(vec![2, 2], a.shape);
     }
 
     #[test]
@@ -35,10 +38,21 @@ mod tensor_tests {
         let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
         let expected = Tensor::from_array(&[&[1.0, 4.0], &[2.0, 5.0], &[3.0, 6.0]]);
 
-        let result = a.transpose();
+        let result = a.transpose(0, 1);
 
         assert_eq!(expected, result);
-        assert_eq!((3, 2), result.size);
+        assert_eq!(vec![3, 2], result.shape);
+    }
+
+    #[test]
+    fn transpose_swaps_any_two_axes() {
+        let a = Tensor::from_shape(&[2, 3, 4], (0..24).map(|x| x as f64).collect());
+
+        let b = a.transpose(0, 2);
+        assert_eq!(vec![4, 3, 2], b.shape);
+        assert_eq!(a[[1, 2, 3]], b[[3, 2, 1]]);
+        assert_eq!(a[[0, 1, 2]], b[[2, 1, 0]]);
+        assert_eq!(a, b.transpose(2, 0));
     }
 
     #[test]
@@ -71,7 +85,7 @@ mod tensor_tests {
         let result = a + b;
 
         assert_eq!(expected, result);
-        assert_eq!(a.size, result.size);
+        assert_eq!(a.shape, result.shape);
     }
 
     #[test]
@@ -83,7 +97,7 @@ mod tensor_tests {
         let result = a - b;
 
         assert_eq!(expected, result);
-        assert_eq!(a.size, result.size);
+        assert_eq!(a.shape, result.shape);
     }
 
     #[test]
@@ -96,7 +110,7 @@ mod tensor_tests {
         let result = a * b;
 
         assert_eq!(expected, result);
-        assert_eq!((2, 3), result.size);
+        assert_eq!(vec![2, 3], result.shape);
     }
 
     #[test]
@@ -108,7 +122,7 @@ mod tensor_tests {
 
         let result = a * b;
         assert_eq!(expected, result);
-        assert_eq!((2, 3), result.size);
+        assert_eq!(vec![2, 3], result.shape);
     }
 
     #[test]
@@ -125,7 +139,7 @@ mod tensor_tests {
         let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]);
         let expected = Tensor::from_array(&[&[2.0, 3.0], &[4.0, 5.0]]);
 
-        let result = a.apply(|i, j, data| data[i][j] + 1.0);
+        let result = a.apply(|index, data| data[index] + 1.0);
         println!("{:}\n{:}", a, result);
 
         assert_eq!(expected, result);
@@ -152,4 +166,666 @@ mod tensor_tests {
 
         assert_eq!(expected, a);
     }
+
+    #[test]
+    fn from_shape_sets_strides() {
+        let a = Tensor::from_shape(&[2, 3, 4], (0..24).map(|x| x as f64).collect());
+
+        assert_eq!(vec![2, 3, 4], a.shape);
+        assert_eq!(vec![12, 4, 1], a.strides);
+        assert_eq!(3, a.rank());
+        assert_eq!(24, a.num_elements());
+    }
+
+    #[test]
+    fn multi_index_returns_correct_value() {
+        let mut a = Tensor::from_shape(&[2, 3, 4], (0..24).map(|x| x as f64).collect());
+
+        assert_eq!(0.0, a[[0, 0, 0]]);
+        assert_eq!(6.0, a[[0, 1, 2]]);
+        assert_eq!(23.0, a[[1, 2, 3]]);
+
+        a[[1, 0, 1]] = -1.0;
+        assert_eq!(-1.0, a[[1, 0, 1]]);
+    }
+
+    #[test]
+    fn elementwise_operations_work_on_higher_rank() {
+        let a = &Tensor::full(&[2, 2, 2], 3.0);
+        let b = &Tensor::full(&[2, 2, 2], 1.0);
+
+        assert_eq!(Tensor::full(&[2, 2, 2], 4.0), a + b);
+        assert_eq!(Tensor::full(&[2, 2, 2], 2.0), a - b);
+        assert_eq!(Tensor::full(&[2, 2, 2], -3.0), -a);
+        assert_eq!(Tensor::full(&[2, 2, 2], 9.0), a.pow(2));
+    }
+
+    #[test]
+    fn display_nests_axes() {
+        let a = Tensor::from_shape(&[2, 1, 2], vec![1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!("[[[1 2]] [[3 4]]]", a.to_string());
+    }
+
+    #[test]
+    fn add_broadcasts_row_over_batch() {
+        let a = &Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0]]);
+        let b = &Tensor::from_array(&[&[10.0, 20.0]]);
+        let expected = Tensor::from_array(&[&[11.0, 22.0], &[13.0, 24.0], &[15.0, 26.0]]);
+
+        let result = a + b;
+
+        assert_eq!(expected, result);
+        assert_eq!(vec![3, 2], result.shape);
+    }
+
+    #[test]
+    fn sub_broadcasts_column_against_row() {
+        let a = &Tensor::from_array(&[&[1.0], &[2.0]]);
+        let b = &Tensor::from_shape(&[3], vec![1.0, 2.0, 3.0]);
+        let expected = Tensor::from_array(&[&[0.0, -1.0, -2.0], &[1.0, 0.0, -1.0]]);
+
+        assert_eq!(expected, a - b);
+    }
+
+    #[test]
+    fn broadcast_shape_aligns_trailing_axes() {
+        assert_eq!(vec![4, 3, 2], broadcast_shape(&[4, 1, 2], &[3, 1]));
+        assert_eq!(vec![2, 3], broadcast_shape(&[2, 3], &[]));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be broadcast")]
+    fn add_rejects_incompatible_shapes() {
+        let _ = &Tensor::fill(2, 3, 1.0) + &Tensor::fill(2, 2, 1.0);
+    }
+
+    #[test]
+    fn sum_to_reduces_broadcast_axes() {
+        let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0]]);
+
+        assert_eq!(Tensor::from_array(&[&[9.0, 12.0]]), a.sum_to(&[1, 2]));
+        assert_eq!(
+            Tensor::from_array(&[&[3.0], &[7.0], &[11.0]]),
+            a.sum_to(&[3, 1])
+        );
+        assert_eq!(Tensor::from_shape(&[2], vec![9.0, 12.0]), a.sum_to(&[2]));
+    }
+
+    #[test]
+    fn mul_elem_returns_elementwise_product() {
+        let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = Tensor::from_array(&[&[2.0, -1.0]]);
+        let expected = Tensor::from_array(&[&[2.0, -2.0], &[6.0, -4.0]]);
+
+        assert_eq!(expected, a.mul_elem(&b));
+    }
+
+    #[test]
+    fn div_returns_elementwise_quotient() {
+        let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = Tensor::from_array(&[&[2.0], &[4.0]]);
+
+        assert_eq!(Tensor::from_array(&[&[0.5, 1.0], &[0.75, 1.0]]), &a / &b);
+        assert_eq!(Tensor::from_array(&[&[0.5, 1.0], &[1.5, 2.0]]), &a / 2.0);
+    }
+
+    #[test]
+    fn scalar_has_rank_zero() {
+        let a = Tensor::scalar(3.0);
+
+        assert_eq!(0, a.rank());
+        assert_eq!(3.0, a.item());
+        assert_eq!(vec![2, 2], (&Tensor::ones(2, 2) + &a).shape);
+    }
+
+    #[test]
+    fn scalar_operations_apply_elementwise() {
+        let a = &Tensor::from_array(&[&[1.0, 2.0], &[4.0, 8.0]]);
+
+        assert_eq!(Tensor::from_array(&[&[2.0, 3.0], &[5.0, 9.0]]), a + 1.0);
+        assert_eq!(Tensor::from_array(&[&[0.0, -1.0], &[-3.0, -7.0]]), 1.0 - a);
+        assert_eq!(Tensor::from_array(&[&[3.0, 6.0], &[12.0, 24.0]]), 3.0 * a);
+        assert_eq!(Tensor::from_array(&[&[8.0, 4.0], &[2.0, 1.0]]), 8.0 / a);
+        assert_eq!(vec![2, 2], (a * 2.0).shape);
+    }
+
+    #[test]
+    fn unary_functions_apply_elementwise() {
+        let a = Tensor::from_array(&[&[1.0, 4.0], &[-9.0, 0.0]]);
+
+        assert_eq!(a.map(f64::exp), a.exp());
+        assert_eq!(Tensor::from_array(&[&[1.0, 4.0], &[9.0, 0.0]]), a.abs());
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0], &[3.0, 0.0]]),
+            a.abs().sqrt()
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[0.0, 4f64.ln()]]),
+            Tensor::from_array(&[&[1.0, 4.0]]).ln()
+        );
+        assert_eq!(a.map(f64::sin), a.sin());
+        assert_eq!(a.map(f64::cos), a.cos());
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 8.0]]),
+            Tensor::from_array(&[&[1.0, 4.0]]).powf(1.5)
+        );
+    }
+
+    #[test]
+    fn reductions_collapse_the_given_axis() {
+        let a = Tensor::from_array(&[&[1.0, 5.0, 3.0], &[4.0, 2.0, 6.0]]);
+
+        assert_eq!(
+            Tensor::from_shape(&[3], vec![5.0, 7.0, 9.0]),
+            a.sum(0, false)
+        );
+        assert_eq!(Tensor::from_array(&[&[3.0], &[4.0]]), a.mean_axis(1, true));
+        assert_eq!(Tensor::from_shape(&[2], vec![5.0, 6.0]), a.max(1, false));
+        assert_eq!(Tensor::from_array(&[&[1.0, 2.0, 3.0]]), a.min(0, true));
+        assert_eq!(Tensor::from_shape(&[2], vec![1, 2]), a.argmax(1, false));
+        assert_eq!(Tensor::from_shape(&[3], vec![0, 1, 0]), a.argmin(0, false));
+    }
+
+    #[test]
+    fn variance_is_biased_or_unbiased() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0, 4.0]]);
+
+        assert_relative_eq!(1.25, a.var(1, false, false).item());
+        assert_relative_eq!(5.0 / 3.0, a.var(1, true, false).item());
+        assert_relative_eq!((5.0f64 / 3.0).sqrt(), a.std(1, true, false).item());
+    }
+
+    #[test]
+    fn argmax_picks_first_of_ties() {
+        let a = Tensor::from_array(&[&[2.0, 7.0, 7.0], &[3.0, 3.0, 1.0]]);
+
+        assert_eq!(Tensor::from_shape(&[2], vec![1, 0]), a.argmax(1, false));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot take argmax over empty axis 1")]
+    fn argmax_rejects_empty_axis() {
+        Tensor::<f64>::from_shape(&[2, 0], vec![]).argmax(1, false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot take max over empty axis 1")]
+    fn max_rejects_empty_axis() {
+        Tensor::<f64>::from_shape(&[2, 0], vec![]).max(1, false);
+    }
+
+    #[test]
+    fn reshape_keeps_row_major_order() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+
+        let b = a.reshape(&[3, 2]);
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0]]),
+            b
+        );
+        assert_eq!(vec![6], a.flatten(0, 1).shape);
+        assert_eq!(vec![1, 2, 3], a.unsqueeze(0).shape);
+        assert_eq!(vec![2, 3, 1], a.unsqueeze(2).shape);
+        assert_eq!(a, a.unsqueeze(1).squeeze(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot reshape")]
+    fn reshape_rejects_different_size() {
+        Tensor::<f64>::zeros(2, 3).reshape(&[4, 2]);
+    }
+
+    #[test]
+    fn permute_reorders_axes() {
+        let a = Tensor::from_shape(&[2, 3, 4], (0..24).map(|x| x as f64).collect());
+
+        let b = a.permute(&[2, 0, 1]);
+        assert_eq!(vec![4, 2, 3], b.shape);
+        for (i, j, k) in [(0, 0, 0), (1, 2, 3), (1, 0, 2), (0, 1, 1)] {
+            assert_eq!(a[[i, j, k]], b[[k, i, j]]);
+        }
+        let m = a.flatten(1, 2);
+        assert_eq!(m.transpose(0, 1), m.permute(&[1, 0]));
+    }
+
+    #[test]
+    fn narrow_and_index_select_pick_entries() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+
+        assert_eq!(
+            Tensor::from_array(&[&[2.0, 3.0], &[5.0, 6.0]]),
+            a.narrow(1, 1, 2)
+        );
+        assert_eq!(Tensor::from_array(&[&[4.0, 5.0, 6.0]]), a.narrow(0, 1, 1));
+        assert_eq!(
+            Tensor::from_array(&[&[3.0, 1.0, 3.0], &[6.0, 4.0, 6.0]]),
+            a.index_select(1, &[2, 0, 2])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn narrow_rejects_out_of_bounds() {
+        Tensor::<f64>::zeros(2, 3).narrow(1, 2, 2);
+    }
+
+    #[test]
+    fn masked_select_flattens_selected_elements() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+        let mask = Tensor::from_array(&[&[false, true, false], &[true, false, true]]);
+
+        assert_eq!(
+            Tensor::from_shape(&[3], vec![2.0, 4.0, 6.0]),
+            a.masked_select(&mask)
+        );
+        assert_eq!(
+            Tensor::from_shape(&[2], vec![1.0, 4.0]),
+            a.masked_select(&Tensor::from_array(&[&[true, false, false]]))
+        );
+    }
+
+    #[test]
+    fn cat_joins_along_axis() {
+        let a = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = Tensor::from_array(&[&[5.0], &[6.0]]);
+
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0, 5.0], &[3.0, 4.0, 6.0]]),
+            Tensor::cat(&[a.clone(), b.clone()], 1)
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0], &[1.0, 2.0]]),
+            Tensor::cat(&[a.clone(), a.narrow(0, 0, 1)], 0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot concatenate")]
+    fn cat_rejects_mismatched_shapes() {
+        Tensor::<f64>::cat(&[Tensor::zeros(2, 2), Tensor::zeros(3, 1)], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn cat_rejects_out_of_range_axis() {
+        Tensor::<f64>::cat(&[Tensor::zeros(2, 2), Tensor::zeros(2, 2)], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn softmax_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).softmax(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 3 out of range")]
+    fn transpose_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).transpose(0, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 5 out of range")]
+    fn squeeze_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 1).squeeze(5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 3 out of range")]
+    fn unsqueeze_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).unsqueeze(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 3 out of range")]
+    fn stack_rejects_out_of_range_axis() {
+        Tensor::<f64>::stack(&[Tensor::zeros(2, 2), Tensor::zeros(2, 2)], 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn narrow_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).narrow(2, 0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn index_select_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).index_select(2, &[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn split_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).split(&[1, 1], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Axis 2 out of range")]
+    fn chunk_rejects_out_of_range_axis() {
+        Tensor::<f64>::zeros(2, 3).chunk(2, 2);
+    }
+
+    #[test]
+    fn stack_adds_an_axis() {
+        let a = Tensor::from_shape(&[2], vec![1.0, 2.0]);
+        let b = Tensor::from_shape(&[2], vec![3.0, 4.0]);
+
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]),
+            Tensor::stack(&[a.clone(), b.clone()], 0)
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 3.0], &[2.0, 4.0]]),
+            Tensor::stack(&[a, b], 1)
+        );
+    }
+
+    #[test]
+    fn split_and_chunk_undo_cat() {
+        let a = Tensor::from_shape(&[2, 5], (0..10).map(|x| x as f64).collect());
+
+        let pieces = a.split(&[2, 3], 1);
+        assert_eq!(vec![2, 3], pieces[1].shape);
+        assert_eq!(a, Tensor::cat(&pieces, 1));
+
+        let chunks = a.chunk(2, 1);
+        assert_eq!(
+            vec![vec![2, 3], vec![2, 2]],
+            chunks.iter().map(|c| c.shape.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(a, Tensor::cat(&chunks, 1));
+        assert_eq!(2, a.chunk(3, 0).len());
+    }
+
+    #[test]
+    fn gather_follows_index_along_axis() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+
+        let rows = Tensor::from_array(&[&[1, 0, 1]]);
+        assert_eq!(Tensor::from_array(&[&[4.0, 2.0, 6.0]]), a.gather(0, &rows));
+        let columns = Tensor::from_array(&[&[2, 2], &[0, 1]]);
+        assert_eq!(
+            Tensor::from_array(&[&[3.0, 3.0], &[4.0, 5.0]]),
+            a.gather(1, &columns)
+        );
+    }
+
+    #[test]
+    fn scatter_add_accumulates_repeated_positions() {
+        let a = Tensor::zeros(2, 3);
+        let index = Tensor::from_array(&[&[0, 1, 0], &[0, 0, 1]]);
+        let src = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+
+        assert_eq!(
+            Tensor::from_array(&[&[5.0, 5.0, 3.0], &[0.0, 2.0, 6.0]]),
+            a.scatter_add(0, &index, &src)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn gather_rejects_out_of_range_index() {
+        Tensor::<f64>::zeros(2, 3).gather(1, &Tensor::from_array(&[&[3], &[0]]));
+    }
+
+    #[test]
+    fn comparisons_return_boolean_masks() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0]]);
+        let b = Tensor::from_array(&[&[2.0], &[1.0]]);
+
+        assert_eq!(
+            Tensor::from_array(&[&[false, false, true], &[false, true, true]]),
+            a.gt(&b)
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[true, false, false], &[false, false, false]]),
+            a.lt(&b)
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[false, true, true], &[true, true, true]]),
+            a.ge(&b)
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[true, true, false]]),
+            a.le(&Tensor::scalar(2.0))
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[false, true, false]]),
+            a.eq(&Tensor::scalar(2.0))
+        );
+    }
+
+    #[test]
+    fn masked_fill_builds_causal_mask() {
+        let scores = Tensor::ones(3, 3);
+        let rows = Tensor::from_array(&[&[0.0], &[1.0], &[2.0]]);
+        let columns = Tensor::from_array(&[&[0.0, 1.0, 2.0]]);
+
+        let masked = scores.masked_fill(&columns.gt(&rows), f64::NEG_INFINITY);
+        let attention = masked.softmax(1);
+
+        assert_eq!(f64::NEG_INFINITY, masked[[0, 1]]);
+        assert_eq!(1.0, masked[[2, 1]]);
+        assert_relative_eq!(1.0, attention[[0, 0]]);
+        assert_relative_eq!(0.5, attention[[1, 1]]);
+        assert_eq!(0.0, attention[[1, 2]]);
+    }
+
+    #[test]
+    fn to_dtype_casts_elements() {
+        let a = Tensor::from_array(&[&[1.75, -2.5, 0.0]]);
+
+        assert_eq!(Tensor::from_array(&[&[1, -2, 0]]), a.to_dtype::<i64>());
+        assert_eq!(
+            Tensor::from_array(&[&[true, true, false]]),
+            a.to_dtype::<bool>()
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[1.75f32, -2.5, 0.0]]),
+            a.to_dtype::<f32>()
+        );
+        assert_eq!(
+            Tensor::from_array(&[&[1.0, 1.0, 0.0]]),
+            a.to_dtype::<bool>().to_dtype::<f64>()
+        );
+    }
+
+    #[test]
+    fn integer_and_boolean_tensors_hold_labels_and_masks() {
+        let labels = Tensor::from_array(&[&[3, 1], &[4, 1]]);
+        let mask = labels.gt(&Tensor::scalar(2));
+
+        assert_eq!(4, labels[[1, 0]]);
+        assert_eq!("[[3 1] [4 1]]", labels.to_string());
+        assert_eq!("[[true false] [true false]]", mask.to_string());
+        assert_eq!(
+            Tensor::from_shape(&[2], vec![0, 0]),
+            labels.argmax(1, false)
+        );
+        assert_eq!(vec![2, 2], Tensor::<bool>::zeros(2, 2).shape);
+    }
+
+    #[test]
+    fn matmul_broadcasts_batch_axes() {
+        let a = Tensor::from_shape(&[2, 2, 2], vec![1.0, 2.0, 3.0, 4.0, 0.0, 1.0, 1.0, 0.0]);
+        let b = Tensor::from_array(&[&[1.0, 1.0], &[0.0, 2.0]]);
+
+        let y = a.matmul(&b);
+        assert_eq!(vec![2, 2, 2], y.shape);
+        assert_eq!(
+            Tensor::from_shape(&[2, 2, 2], vec![1.0, 5.0, 3.0, 11.0, 0.0, 2.0, 1.0, 1.0]),
+            y
+        );
+        assert_eq!(
+            a.narrow(0, 1, 1).squeeze(0).matmul(&b),
+            y.narrow(0, 1, 1).squeeze(0)
+        );
+
+        let batch = Tensor::from_shape(&[3, 1, 2, 2], (0..12).map(|x| x as f64).collect());
+        assert_eq!(vec![3, 2, 2, 2], batch.matmul(&a).shape);
+    }
+
+    #[test]
+    fn matmul_handles_vectors() {
+        let v = Tensor::from_shape(&[2], vec![1.0, 2.0]);
+        let m = Tensor::from_array(&[&[1.0, 2.0], &[3.0, 4.0]]);
+
+        assert_eq!(Tensor::from_shape(&[2], vec![7.0, 10.0]), v.matmul(&m));
+        assert_eq!(Tensor::from_shape(&[2], vec![5.0, 11.0]), m.matmul(&v));
+        assert_eq!(Tensor::scalar(5.0), v.matmul(&v));
+        assert_eq!(m.matmul(&m), &m * &m);
+    }
+
+    #[test]
+    fn matmul_handles_empty_axes() {
+        let y = Tensor::<f64>::zeros(0, 3).matmul(&Tensor::ones(3, 4));
+        assert_eq!(vec![0, 4], y.shape);
+        let y = Tensor::<f64>::zeros(2, 3).matmul(&Tensor::ones(3, 0));
+        assert_eq!(vec![2, 0], y.shape);
+        let batch = Tensor::<f64>::from_shape(&[0, 2, 3], vec![]);
+        assert_eq!(vec![0, 2, 4], batch.matmul(&Tensor::ones(3, 4)).shape);
+        let y = Tensor::<f64>::zeros(2, 0).matmul(&Tensor::ones(0, 3));
+        assert_eq!(Tensor::zeros(2, 3), y);
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible dimensions")]
+    fn matmul_rejects_mismatched_inner_dimensions() {
+        Tensor::<f64>::zeros(2, 3).matmul(&Tensor::zeros(2, 3));
+    }
+
+    #[test]
+    fn tiled_matmul_matches_naive_on_ragged_sizes() {
+        // Sizes straddle the tile edge and are large enough to split across threads
+        for (m, n, p) in [(1, 1, 1), (3, 65, 2), (70, 129, 67)] {
+            let a: Vec<f64> = (0..m * n).map(|i| (i % 7) as f64 - 3.0).collect();
+            let b: Vec<f64> = (0..n * p).map(|i| (i % 5) as f64 * 0.5).collect();
+            let (mut expected, mut actual) = (vec![1.0; m * p], vec![1.0; m * p]);
+
+            matmul::naive(&a, &b, &mut expected, (m, n, p));
+            matmul::tiled(&a, &b, &mut actual, (m, n, p));
+
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn simd_kernels_match_scalar_on_ragged_lengths() {
+        // 11 elements leave a tail after every register width
+        let a: Vec<f64> = (0..11).map(|i| i as f64 * 0.75 - 4.0).collect();
+        let b: Vec<f64> = (0..11).map(|i| 2.0 - i as f64 * 0.5).collect();
+        let expect = |actual: Vec<f64>, fun: &dyn Fn(usize) -> f64| {
+            for (i, x) in actual.into_iter().enumerate() {
+                assert_relative_eq!(fun(i), x, max_relative = 1e-12);
+            }
+        };
+
+        expect(simd::add(&a, &b), &|i| a[i] + b[i]);
+        expect(simd::sub(&a, &b), &|i| a[i] - b[i]);
+        expect(simd::neg(&a), &|i| -a[i]);
+        expect(simd::relu(&a), &|i| a[i].max(0.0));
+        expect(simd::powi(&a, 3), &|i| a[i].powi(3));
+        expect(simd::powi(&a, -2), &|i| a[i].powi(-2));
+        let mut sum = a.clone();
+        simd::add_assign(&mut sum, &b);
+        expect(sum, &|i| a[i] + b[i]);
+        let mut difference = a.clone();
+        simd::sub_assign(&mut difference, &b);
+        expect(difference, &|i| a[i] - b[i]);
+
+        let single: Vec<f32> = a.iter().map(|&x| x as f32).collect();
+        let expected: Vec<f32> = single.iter().map(|x| x.max(0.0)).collect();
+        assert_eq!(expected, simd::relu(&single));
+
+        // Equal inputs give identical bits whether they sit in a register or in the tail
+        let mut c = vec![1.1f64; 11];
+        (c[0], c[10]) = (-0.0, -0.0);
+        (c[1], c[9]) = (1.7, 1.7);
+        let relu = simd::relu(&c);
+        assert_eq!(0.0f64.to_bits(), relu[0].to_bits());
+        assert_eq!(0.0f64.to_bits(), relu[10].to_bits());
+        for exp in [7, -3] {
+            let powers = simd::powi(&c, exp);
+            assert_eq!(powers[1].to_bits(), powers[9].to_bits());
+        }
+        let single: Vec<f32> = c.iter().map(|&x| x as f32).collect();
+        let relu = simd::relu(&single);
+        assert_eq!(0.0f32.to_bits(), relu[0].to_bits());
+        assert_eq!(0.0f32.to_bits(), relu[10].to_bits());
+    }
+
+    #[test]
+    #[should_panic(expected = "Buffers must have the same length")]
+    fn simd_trait_kernels_reject_short_operands() {
+        let backing = [0.0f64; 8];
+        <f64 as simd::Simd>::add(&[0.0; 8], &backing[..1]);
+    }
+
+    #[test]
+    fn clones_and_reshapes_share_storage_until_written() {
+        let a = Tensor::from_shape(&[2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let mut b = a.clone();
+        let c = a.reshape(&[3, 2]);
+        assert!(b.data.shares(&a.data));
+        assert!(c.data.shares(&a.data));
+
+        b[[0, 0]] = 9.0;
+
+        assert!(!b.data.shares(&a.data));
+        assert_eq!(1.0, a[[0, 0]]);
+        assert_eq!(1.0, c[[0, 0]]);
+        assert_eq!(9.0, b[[0, 0]]);
+    }
+
+    #[test]
+    fn exact_gelu_is_accurate_to_double_precision() {
+        // x * Φ(x) with Φ(1) = 0.841344746068542948585...
+        let x = Tensor::from_array(&[&[1.0, -1.0, 0.0]]);
+        let y = x.gelu(GeluApproximation::None);
+
+        assert_relative_eq!(0.8413447460685429, y[[0, 0]], max_relative = 1e-15);
+        assert_relative_eq!(-0.15865525393145707, y[[0, 1]], max_relative = 1e-14);
+        assert_eq!(0.0, y[[0, 2]]);
+    }
+
+    #[test]
+    fn softmax_normalizes_each_lane() {
+        let a = Tensor::from_array(&[&[1.0, 2.0, 3.0], &[1.0, 1.0, 1.0]]);
+
+        let rows = a.softmax(1);
+        let e = [1f64.exp(), 2f64.exp(), 3f64.exp()];
+        let total: f64 = e.iter().sum();
+        assert_relative_eq!(e[2] / total, rows[[0, 2]]);
+        assert_relative_eq!(1.0 / 3.0, rows[[1, 0]]);
+
+        let columns = a.softmax(0);
+        assert_relative_eq!(1.0, columns[[0, 1]] + columns[[1, 1]]);
+        assert_relative_eq!(0.5, columns[[0, 0]]);
+    }
+
+    #[test]
+    fn softmax_is_stable_for_large_inputs() {
+        let a = Tensor::from_array(&[&[1000.0, 1000.0], &[-1000.0, 0.0]]);
+
+        assert_eq!(
+            Tensor::fill(1, 2, 0.5),
+            Tensor::from_array(&[&a[0]]).softmax(1)
+        );
+        let log = a.log_softmax(1);
+        assert_relative_eq!(-(2f64.ln()), log[[0, 0]]);
+        assert_relative_eq!(-1000.0, log[[1, 0]]);
+        assert_relative_eq!(0.0, log[[1, 1]]);
+    }
+
+    #[test]
+    fn log_softmax_matches_log_of_softmax() {
+        let a = Tensor::from_shape(&[2, 2, 3], (0..12).map(|x| (x as f64).sin()).collect());
+
+        let expected = a.softmax(1).ln();
+        let result = a.log_softmax(1);
+        for (x, y) in expected.data.iter().zip(result.data.iter()) {
+            assert_relative_eq!(x, y, epsilon = 1e-12);
+        }
+    }
 }